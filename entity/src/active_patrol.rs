@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "active_patrols")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub officer_id: u64,
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub start: DateTime,
+    pub end: Option<DateTime>,
+    pub last_seen: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "active_patrol_mutes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub officer_id: u64,
+    pub start: DateTime,
+    pub end: Option<DateTime>,
+    pub deafened: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
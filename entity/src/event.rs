@@ -7,10 +7,50 @@ pub struct Model {
     pub id: i32,
     pub start: DateTime,
     pub end: DateTime,
-    pub hosts: String,
+    pub name: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    /// The saved voice channel the event takes place in
+    pub channel_id: Option<i32>,
+    pub cancelled_at: Option<DateTime>,
+    /// The id of the Discord scheduled event this event is synced with
+    #[sea_orm(unique)]
+    pub discord_event_id: Option<u64>,
+    /// The template of a recurring event this event was created from
+    pub template_id: Option<i32>,
+    /// The day of the recurring event this event is for, kept when the event is moved or cancelled
+    /// so it isn't created again
+    pub occurrence_date: Option<Date>,
+}
+
+impl Related<super::event_host::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hosts.def()
+    }
+}
+
+impl Related<super::event_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Template.def()
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::event_host::Entity")]
+    Hosts,
+    #[sea_orm(
+        belongs_to = "super::saved_voice_channel::Entity",
+        from = "Column::ChannelId",
+        to = "super::saved_voice_channel::Column::Id"
+    )]
+    Channel,
+    #[sea_orm(
+        belongs_to = "super::event_template::Entity",
+        from = "Column::TemplateId",
+        to = "super::event_template::Column::Id"
+    )]
+    Template,
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,50 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "event_templates")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    /// When the events repeat, like "weekly mon,thu", "every 3 days" or "monthly last fri"
+    pub recurrence: String,
+    /// The first day an event can take place on, every N days counts from this day
+    pub first_date: Date,
+    pub start_time: Time,
+    pub duration_minutes: i32,
+    /// The saved voice channel the events take place in
+    pub channel_id: Option<i32>,
+    /// The last day events have been created up to
+    pub created_until: Option<Date>,
+    pub ended_at: Option<DateTime>,
+}
+
+impl Related<super::event_template_host::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hosts.def()
+    }
+}
+
+impl Related<super::event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Events.def()
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::event_template_host::Entity")]
+    Hosts,
+    #[sea_orm(has_many = "super::event::Entity")]
+    Events,
+    #[sea_orm(
+        belongs_to = "super::saved_voice_channel::Entity",
+        from = "Column::ChannelId",
+        to = "super::saved_voice_channel::Column::Id"
+    )]
+    Channel,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "event_template_hosts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub template_id: i32,
+    pub officer_id: u64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::event_template::Entity",
+        from = "Column::TemplateId",
+        to = "super::event_template::Column::Id"
+    )]
+    Template,
+    #[sea_orm(
+        belongs_to = "super::officer::Entity",
+        from = "Column::OfficerId",
+        to = "super::officer::Column::Id"
+    )]
+    Officer,
+}
+
+impl Related<super::event_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Template.def()
+    }
+}
+
+impl Related<super::officer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Officer.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
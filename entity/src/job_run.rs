@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "job_runs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub job_name: String,
+    /// The time the run was scheduled for, which is earlier than the start when catching up
+    pub scheduled_for: DateTime,
+    pub started_at: DateTime,
+    /// Only set once the run is over, whether it failed or not
+    pub finished_at: Option<DateTime>,
+    /// Why the run failed, this isn't set for runs that succeeded
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
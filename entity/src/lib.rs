@@ -1,7 +1,20 @@
 pub use sea_orm;
 
+pub mod active_patrol;
+pub mod active_patrol_mute;
 pub mod event;
+pub mod event_host;
+pub mod event_template;
+pub mod event_template_host;
+pub mod job_run;
+pub mod loa;
+pub mod notification;
 pub mod officer;
 pub mod patrol;
+pub mod patrol_adjustment;
 pub mod patrol_voice;
+pub mod patrol_voice_mute;
 pub mod saved_voice_channel;
+pub mod saved_voice_channel_name;
+pub mod status_board;
+pub mod time_claim;
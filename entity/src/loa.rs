@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "loas")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub officer_id: u64,
+    /// First day of the leave
+    pub start: Date,
+    /// Last day of the leave
+    pub end: Date,
+    pub reason: String,
+    /// The leadership member that approved the leave, the leave is still pending if this isn't set
+    pub approved_by: Option<u64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::officer::Entity",
+        from = "Column::OfficerId",
+        to = "super::officer::Column::Id"
+    )]
+    Officer,
+}
+
+impl Related<super::officer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Officer.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
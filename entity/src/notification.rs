@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "notifications")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub officer_id: u64,
+    /// What the officer was notified about, like which activity warning it was
+    pub kind: String,
+    /// First day of the requirement period the notification is about
+    pub period_start: Date,
+    pub sent_at: DateTime,
+    /// Whether the officer got a direct message, they were pinged in a channel instead otherwise
+    pub via_dm: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::officer::Entity",
+        from = "Column::OfficerId",
+        to = "super::officer::Column::Id"
+    )]
+    Officer,
+}
+
+impl Related<super::officer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Officer.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -6,10 +6,15 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub officer_id: u64,
-    pub main_channel_id: i32,
+    /// Not set for patrols that were entered by hand
+    pub main_channel_id: Option<i32>,
     pub start: DateTime,
     pub end: DateTime,
     pub event_id: Option<i32>,
+    pub ignored: bool,
+    /// The supervisor that entered the patrol by hand, not set for patrols tracked by the bot
+    pub manual_by: Option<u64>,
+    pub reason: Option<String>,
 }
 
 impl Related<super::patrol_voice::Entity> for Entity {
@@ -18,10 +23,24 @@ impl Related<super::patrol_voice::Entity> for Entity {
     }
 }
 
+impl Related<super::patrol_adjustment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Adjustments.def()
+    }
+}
+
+impl Related<super::event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Event.def()
+    }
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::patrol_voice::Entity")]
     PatrolVoiceComms,
+    #[sea_orm(has_many = "super::patrol_adjustment::Entity")]
+    Adjustments,
     #[sea_orm(
         belongs_to = "super::saved_voice_channel::Entity",
         from = "Column::MainChannelId",
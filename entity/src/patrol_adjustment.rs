@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+
+/// A change a supervisor made to the patrol time of an officer by hand.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "patrol_adjustments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub patrol_id: i32,
+    pub officer_id: u64,
+    pub supervisor_id: u64,
+    /// One of "add_time", "remove_time" or "undo"
+    pub action: String,
+    #[sea_orm(column_type = "Text")]
+    pub reason: String,
+    /// The adjustment this one undoes, only set for the "undo" action
+    pub undoes: Option<i32>,
+    /// The part of the patrol that was removed in seconds, the whole patrol is removed if it isn't
+    /// set. Only used by the "remove_time" action.
+    pub seconds: Option<i64>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patrol::Entity",
+        from = "Column::PatrolId",
+        to = "super::patrol::Column::Id"
+    )]
+    Patrol,
+}
+
+impl Related<super::patrol::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Patrol.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -17,6 +17,12 @@ impl Related<super::patrol::Entity> for Entity {
     }
 }
 
+impl Related<super::patrol_voice_mute::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Mutes.def()
+    }
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
@@ -31,6 +37,8 @@ pub enum Relation {
         to = "super::patrol::Column::Id"
     )]
     Patrol,
+    #[sea_orm(has_many = "super::patrol_voice_mute::Entity")]
+    Mutes,
 }
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "patrol_voice_mutes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub patrol_voice_id: i32,
+    pub start: DateTime,
+    pub end: DateTime,
+    pub deafened: bool,
+}
+
+impl Related<super::patrol_voice::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PatrolVoice.def()
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::patrol_voice::Entity",
+        from = "Column::PatrolVoiceId",
+        to = "super::patrol_voice::Column::Id"
+    )]
+    PatrolVoice,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -7,10 +7,24 @@ pub struct Model {
     pub id: i32,
     pub channel_id: u64,
     pub guild_id: u64,
+    /// The current name of the channel, or the last one it had if it was deleted
     pub name: String,
+    pub category_id: Option<u64>,
+    /// The name of the Discord channel type, like "voice" or "stage"
+    pub channel_type: Option<String>,
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::saved_voice_channel_name::Entity")]
+    Names,
+}
+
+impl Related<super::saved_voice_channel_name::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Names.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+
+/// A name a saved voice channel had before it was renamed.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "saved_voice_channel_names")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub saved_voice_channel_id: i32,
+    pub name: String,
+    /// When the channel was renamed, the name was used up to this time
+    pub renamed_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::saved_voice_channel::Entity",
+        from = "Column::SavedVoiceChannelId",
+        to = "super::saved_voice_channel::Column::Id"
+    )]
+    SavedVoiceChannel,
+}
+
+impl Related<super::saved_voice_channel::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SavedVoiceChannel.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,48 @@
+use sea_orm::entity::prelude::*;
+
+/// Patrol time an officer says is missing, like when the bot was offline while they patrolled.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "time_claims")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub officer_id: u64,
+    pub start: DateTime,
+    pub end: DateTime,
+    #[sea_orm(column_type = "Text")]
+    pub reason: String,
+    /// One of "pending", "approved" or "denied"
+    pub status: String,
+    pub reviewed_by: Option<u64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub review_reason: Option<String>,
+    /// The patrol that was created when the claim was approved
+    pub patrol_id: Option<i32>,
+    /// The message in the review channel with the buttons to approve or deny the claim
+    pub review_message_id: Option<u64>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::officer::Entity",
+        from = "Column::OfficerId",
+        to = "super::officer::Column::Id"
+    )]
+    Officer,
+    #[sea_orm(
+        belongs_to = "super::patrol::Entity",
+        from = "Column::PatrolId",
+        to = "super::patrol::Column::Id"
+    )]
+    Patrol,
+}
+
+impl Related<super::officer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Officer.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -6,6 +6,21 @@ mod m20220416_000003_add_patrols;
 mod m20220416_000004_fix_patrols;
 mod m20220418_000005_fix_events;
 mod m20220424_000006_add_patrol_voice;
+mod m20220501_000007_add_active_patrols;
+mod m20220502_000008_add_patrol_ignored;
+mod m20220505_000009_add_voice_mutes;
+mod m20220510_000010_add_loas;
+mod m20220512_000011_add_job_runs;
+mod m20220514_000012_add_notifications;
+mod m20220516_000013_add_channel_metadata;
+mod m20220518_000014_add_status_boards;
+mod m20220520_000015_add_patrol_adjustments;
+mod m20220522_000016_add_time_claims;
+mod m20220524_000017_add_event_details;
+mod m20220526_000018_add_event_discord_ids;
+mod m20220528_000019_normalise_event_hosts;
+mod m20220530_000020_add_event_templates;
+mod m20220601_000021_add_adjustment_seconds;
 
 pub struct Migrator;
 
@@ -19,6 +34,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20220416_000004_fix_patrols::Migration),
             Box::new(m20220418_000005_fix_events::Migration),
             Box::new(m20220424_000006_add_patrol_voice::Migration),
+            Box::new(m20220501_000007_add_active_patrols::Migration),
+            Box::new(m20220502_000008_add_patrol_ignored::Migration),
+            Box::new(m20220505_000009_add_voice_mutes::Migration),
+            Box::new(m20220510_000010_add_loas::Migration),
+            Box::new(m20220512_000011_add_job_runs::Migration),
+            Box::new(m20220514_000012_add_notifications::Migration),
+            Box::new(m20220516_000013_add_channel_metadata::Migration),
+            Box::new(m20220518_000014_add_status_boards::Migration),
+            Box::new(m20220520_000015_add_patrol_adjustments::Migration),
+            Box::new(m20220522_000016_add_time_claims::Migration),
+            Box::new(m20220524_000017_add_event_details::Migration),
+            Box::new(m20220526_000018_add_event_discord_ids::Migration),
+            Box::new(m20220528_000019_normalise_event_hosts::Migration),
+            Box::new(m20220530_000020_add_event_templates::Migration),
+            Box::new(m20220601_000021_add_adjustment_seconds::Migration),
         ]
     }
 }
@@ -21,7 +21,7 @@ impl MigrationTrait for Migration {
                 .col(ColumnDef::new(event::Column::Id).integer().not_null().primary_key().auto_increment())
                 .col(ColumnDef::new(event::Column::Start).date_time().not_null())
                 .col(ColumnDef::new(event::Column::End).date_time().not_null())
-                .col(ColumnDef::new(event::Column::Hosts).text().not_null())
+                .col(ColumnDef::new(Alias::new("hosts")).text().not_null())
                 .to_owned(),
         ).await?;
         
@@ -0,0 +1,56 @@
+use entity::active_patrol;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220501_000007_add_active_patrols"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(active_patrol::Entity)
+                .col(ColumnDef::new(active_patrol::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(active_patrol::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(active_patrol::Column::GuildId).big_unsigned().not_null())
+                .col(ColumnDef::new(active_patrol::Column::ChannelId).big_unsigned().not_null())
+                .col(ColumnDef::new(active_patrol::Column::Start).date_time().not_null())
+                .col(ColumnDef::new(active_patrol::Column::End).date_time())
+                .col(ColumnDef::new(active_patrol::Column::LastSeen).date_time().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_index(
+            Index::create()
+                .name("index-active_patrol-officer")
+                .table(active_patrol::Entity)
+                .col(active_patrol::Column::OfficerId)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("index-active_patrol-officer")
+                .table(active_patrol::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(active_patrol::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
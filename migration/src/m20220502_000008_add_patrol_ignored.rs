@@ -0,0 +1,36 @@
+use entity::patrol;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220502_000008_add_patrol_ignored"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .add_column(ColumnDef::new(patrol::Column::Ignored).boolean().not_null().default(false))
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .drop_column(patrol::Column::Ignored)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use entity::active_patrol_mute;
+use entity::patrol_voice;
+use entity::patrol_voice_mute;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220505_000009_add_voice_mutes"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(patrol_voice_mute::Entity)
+                .col(ColumnDef::new(patrol_voice_mute::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(patrol_voice_mute::Column::PatrolVoiceId).integer().not_null())
+                .col(ColumnDef::new(patrol_voice_mute::Column::Start).date_time().not_null())
+                .col(ColumnDef::new(patrol_voice_mute::Column::End).date_time().not_null())
+                .col(ColumnDef::new(patrol_voice_mute::Column::Deafened).boolean().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-patrol_voice_mute-patrol_voice")
+                .from(patrol_voice_mute::Entity, patrol_voice_mute::Column::PatrolVoiceId)
+                .to(patrol_voice::Entity, patrol_voice::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_table(
+            Table::create()
+                .table(active_patrol_mute::Entity)
+                .col(ColumnDef::new(active_patrol_mute::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(active_patrol_mute::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(active_patrol_mute::Column::Start).date_time().not_null())
+                .col(ColumnDef::new(active_patrol_mute::Column::End).date_time())
+                .col(ColumnDef::new(active_patrol_mute::Column::Deafened).boolean().not_null())
+                .to_owned(),
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(active_patrol_mute::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-patrol_voice_mute-patrol_voice")
+                .table(patrol_voice_mute::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(patrol_voice_mute::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
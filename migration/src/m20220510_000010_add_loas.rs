@@ -0,0 +1,58 @@
+use entity::loa;
+use entity::officer;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220510_000010_add_loas"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(loa::Entity)
+                .col(ColumnDef::new(loa::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(loa::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(loa::Column::Start).date().not_null())
+                .col(ColumnDef::new(loa::Column::End).date().not_null())
+                .col(ColumnDef::new(loa::Column::Reason).text().not_null())
+                .col(ColumnDef::new(loa::Column::ApprovedBy).big_unsigned())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-loa-officer")
+                .from(loa::Entity, loa::Column::OfficerId)
+                .to(officer::Entity, officer::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-loa-officer")
+                .table(loa::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(loa::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
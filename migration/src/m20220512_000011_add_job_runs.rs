@@ -0,0 +1,49 @@
+use entity::job_run;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220512_000011_add_job_runs"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(job_run::Entity)
+                .col(ColumnDef::new(job_run::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(job_run::Column::JobName).string().not_null())
+                .col(ColumnDef::new(job_run::Column::ScheduledFor).date_time().not_null())
+                .col(ColumnDef::new(job_run::Column::StartedAt).date_time().not_null())
+                .col(ColumnDef::new(job_run::Column::FinishedAt).date_time())
+                .col(ColumnDef::new(job_run::Column::Error).text())
+                .to_owned(),
+        ).await?;
+
+        manager.create_index(
+            Index::create()
+                .name("index-job_run-job_name-scheduled_for")
+                .table(job_run::Entity)
+                .col(job_run::Column::JobName)
+                .col(job_run::Column::ScheduledFor)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(job_run::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
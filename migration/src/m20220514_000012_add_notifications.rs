@@ -0,0 +1,70 @@
+use entity::notification;
+use entity::officer;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220514_000012_add_notifications"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(notification::Entity)
+                .col(ColumnDef::new(notification::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(notification::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(notification::Column::Kind).string().not_null())
+                .col(ColumnDef::new(notification::Column::PeriodStart).date().not_null())
+                .col(ColumnDef::new(notification::Column::SentAt).date_time().not_null())
+                .col(ColumnDef::new(notification::Column::ViaDm).boolean().not_null())
+                .to_owned(),
+        ).await?;
+
+        // Nobody gets the same notification twice for one period
+        manager.create_index(
+            Index::create()
+                .name("index-notification-officer-kind-period")
+                .table(notification::Entity)
+                .col(notification::Column::OfficerId)
+                .col(notification::Column::Kind)
+                .col(notification::Column::PeriodStart)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-notification-officer")
+                .from(notification::Entity, notification::Column::OfficerId)
+                .to(officer::Entity, officer::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-notification-officer")
+                .table(notification::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(notification::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
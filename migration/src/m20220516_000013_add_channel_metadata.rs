@@ -0,0 +1,98 @@
+use entity::saved_voice_channel;
+use entity::saved_voice_channel_name;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220516_000013_add_channel_metadata"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .add_column(ColumnDef::new(saved_voice_channel::Column::CategoryId).big_unsigned())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .add_column(ColumnDef::new(saved_voice_channel::Column::ChannelType).string())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .add_column(ColumnDef::new(saved_voice_channel::Column::DeletedAt).date_time())
+                .to_owned()
+        ).await?;
+
+        manager.create_table(
+            Table::create()
+                .table(saved_voice_channel_name::Entity)
+                .col(ColumnDef::new(saved_voice_channel_name::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(saved_voice_channel_name::Column::SavedVoiceChannelId).integer().not_null())
+                .col(ColumnDef::new(saved_voice_channel_name::Column::Name).string().not_null())
+                .col(ColumnDef::new(saved_voice_channel_name::Column::RenamedAt).date_time().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-saved_voice_channel_name-saved_voice_channel")
+                .from(saved_voice_channel_name::Entity, saved_voice_channel_name::Column::SavedVoiceChannelId)
+                .to(saved_voice_channel::Entity, saved_voice_channel::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-saved_voice_channel_name-saved_voice_channel")
+                .table(saved_voice_channel_name::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(saved_voice_channel_name::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .drop_column(saved_voice_channel::Column::CategoryId)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .drop_column(saved_voice_channel::Column::ChannelType)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(saved_voice_channel::Entity)
+                .drop_column(saved_voice_channel::Column::DeletedAt)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
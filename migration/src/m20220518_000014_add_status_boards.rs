@@ -0,0 +1,37 @@
+use entity::status_board;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220518_000014_add_status_boards"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(status_board::Entity)
+                .col(ColumnDef::new(status_board::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(status_board::Column::ChannelId).big_unsigned().not_null().unique_key())
+                .col(ColumnDef::new(status_board::Column::MessageId).big_unsigned().not_null())
+                .to_owned(),
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(status_board::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
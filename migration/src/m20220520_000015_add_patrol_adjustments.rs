@@ -0,0 +1,113 @@
+use entity::patrol;
+use entity::patrol_adjustment;
+use entity::sea_orm::ConnectionTrait;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220520_000015_add_patrol_adjustments"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Patrols entered by hand don't have a voice channel
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .modify_column(ColumnDef::new(patrol::Column::MainChannelId).integer())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .add_column(ColumnDef::new(patrol::Column::ManualBy).big_unsigned())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .add_column(ColumnDef::new(patrol::Column::Reason).text())
+                .to_owned()
+        ).await?;
+
+        manager.create_table(
+            Table::create()
+                .table(patrol_adjustment::Entity)
+                .col(ColumnDef::new(patrol_adjustment::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(patrol_adjustment::Column::PatrolId).integer().not_null())
+                .col(ColumnDef::new(patrol_adjustment::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(patrol_adjustment::Column::SupervisorId).big_unsigned().not_null())
+                .col(ColumnDef::new(patrol_adjustment::Column::Action).string().not_null())
+                .col(ColumnDef::new(patrol_adjustment::Column::Reason).text().not_null())
+                .col(ColumnDef::new(patrol_adjustment::Column::Undoes).integer())
+                .col(ColumnDef::new(patrol_adjustment::Column::CreatedAt).date_time().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-patrol_adjustment-patrol")
+                .from(patrol_adjustment::Entity, patrol_adjustment::Column::PatrolId)
+                .to(patrol::Entity, patrol::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-patrol_adjustment-patrol")
+                .table(patrol_adjustment::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(patrol_adjustment::Entity)
+                .to_owned()
+        ).await?;
+
+        // Patrols entered by hand have no voice channel, they can't be kept once it is required
+        let conn = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let delete = Query::delete()
+            .from_table(Alias::new("patrols"))
+            .and_where(Expr::col(Alias::new("main_channel_id")).is_null())
+            .to_owned();
+        conn.execute(backend.build(&delete)).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .modify_column(ColumnDef::new(patrol::Column::MainChannelId).integer().not_null())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .drop_column(patrol::Column::ManualBy)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(patrol::Entity)
+                .drop_column(patrol::Column::Reason)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use entity::officer;
+use entity::patrol;
+use entity::time_claim;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220522_000016_add_time_claims"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(time_claim::Entity)
+                .col(ColumnDef::new(time_claim::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(time_claim::Column::OfficerId).big_unsigned().not_null())
+                .col(ColumnDef::new(time_claim::Column::Start).date_time().not_null())
+                .col(ColumnDef::new(time_claim::Column::End).date_time().not_null())
+                .col(ColumnDef::new(time_claim::Column::Reason).text().not_null())
+                .col(ColumnDef::new(time_claim::Column::Status).string().not_null())
+                .col(ColumnDef::new(time_claim::Column::ReviewedBy).big_unsigned())
+                .col(ColumnDef::new(time_claim::Column::ReviewReason).text())
+                .col(ColumnDef::new(time_claim::Column::PatrolId).integer())
+                .col(ColumnDef::new(time_claim::Column::ReviewMessageId).big_unsigned())
+                .col(ColumnDef::new(time_claim::Column::CreatedAt).date_time().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-time_claim-officer")
+                .from(time_claim::Entity, time_claim::Column::OfficerId)
+                .to(officer::Entity, officer::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-time_claim-patrol")
+                .from(time_claim::Entity, time_claim::Column::PatrolId)
+                .to(patrol::Entity, patrol::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-time_claim-patrol")
+                .table(time_claim::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-time_claim-officer")
+                .table(time_claim::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(time_claim::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use entity::event;
+use entity::event_host;
+use entity::officer;
+use entity::saved_voice_channel;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220524_000017_add_event_details"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .add_column(ColumnDef::new(event::Column::Name).string().not_null().default(""))
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .add_column(ColumnDef::new(event::Column::Description).text())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .add_column(ColumnDef::new(event::Column::ChannelId).integer())
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .add_column(ColumnDef::new(event::Column::CancelledAt).date_time())
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event-saved_voice_channel")
+                .from(event::Entity, event::Column::ChannelId)
+                .to(saved_voice_channel::Entity, saved_voice_channel::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_table(
+            Table::create()
+                .table(event_host::Entity)
+                .col(ColumnDef::new(event_host::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(event_host::Column::EventId).integer().not_null())
+                .col(ColumnDef::new(event_host::Column::OfficerId).big_unsigned().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_index(
+            Index::create()
+                .name("index-event_host-event-officer")
+                .table(event_host::Entity)
+                .col(event_host::Column::EventId)
+                .col(event_host::Column::OfficerId)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event_host-event")
+                .from(event_host::Entity, event_host::Column::EventId)
+                .to(event::Entity, event::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event_host-officer")
+                .from(event_host::Entity, event_host::Column::OfficerId)
+                .to(officer::Entity, officer::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event_host-officer")
+                .table(event_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event_host-event")
+                .table(event_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(event_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event-saved_voice_channel")
+                .table(event::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .drop_column(event::Column::Name)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .drop_column(event::Column::Description)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .drop_column(event::Column::ChannelId)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .drop_column(event::Column::CancelledAt)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use entity::event;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220526_000018_add_event_discord_ids"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .add_column(ColumnDef::new(event::Column::DiscordEventId).big_unsigned())
+                .to_owned()
+        ).await?;
+
+        manager.create_index(
+            Index::create()
+                .name("index-event-discord_event_id")
+                .table(event::Entity)
+                .col(event::Column::DiscordEventId)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("index-event-discord_event_id")
+                .table(event::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(event::Entity)
+                .drop_column(event::Column::DiscordEventId)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
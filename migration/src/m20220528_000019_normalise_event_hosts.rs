@@ -0,0 +1,198 @@
+use entity::sea_orm::ConnectionTrait;
+use sea_schema::migration::prelude::*;
+use std::collections::HashMap;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220528_000019_normalise_event_hosts"
+    }
+}
+
+/// The free text hosts column the hosts were stored in before the event_hosts table.
+fn hosts_column() -> Alias {
+    Alias::new("hosts")
+}
+
+/// An officer as it is needed to recognise them in the free text hosts, the id and VRChat name.
+type Officer = (u64, String);
+
+/// Read a Discord id, either on its own or as a mention like <@123> or <@!123>.
+fn parse_id(word: &str) -> Option<u64> {
+    let id = word.trim_start_matches("<@").trim_start_matches('!').trim_end_matches('>');
+    match !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+        true => id.parse().ok(),
+        false => None,
+    }
+}
+
+/// Find the officers in the free text hosts of an event, returning the parts that aren't
+/// recognised as well.
+///
+/// Hosts are separated by commas, slashes, ampersands, semicolons, new lines or "and", and are
+/// either Discord ids, mentions or VRChat names.
+fn parse_hosts(hosts: &str, officers: &[Officer]) -> (Vec<u64>, Vec<String>) {
+    let (mut ids, mut unknown) = (Vec::new(), Vec::new());
+    let hosts = hosts.replace(" and ", ",");
+    for part in hosts.split([',', '/', '&', ';', '\n']).map(str::trim) {
+        if part.is_empty() {
+            continue;
+        }
+
+        let words: Vec<_> = part.split_whitespace().collect();
+        let found: Vec<_> = match words.iter().map(|word| parse_id(word)).collect() {
+            Some(part_ids) => part_ids,
+            None => officers
+                .iter()
+                .filter(|(_, vrchat_name)| vrchat_name.eq_ignore_ascii_case(part))
+                .map(|(id, _)| *id)
+                .collect(),
+        };
+        let found: Vec<_> =
+            found.into_iter().filter(|id| officers.iter().any(|(o_id, _)| o_id == id)).collect();
+
+        if found.is_empty() {
+            unknown.push(part.to_owned());
+        }
+        for id in found {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    (ids, unknown)
+}
+
+/// Write the hosts of an event back as free text, as mentions separated by commas.
+fn join_hosts(hosts: &[u64]) -> String {
+    let mentions: Vec<_> = hosts.iter().map(|id| format!("<@{}>", id)).collect();
+    mentions.join(", ")
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // The tables are queried by name so this doesn't depend on the entities as they are now
+        let select = Query::select()
+            .columns([Alias::new("id"), Alias::new("vrchat_name")])
+            .from(Alias::new("officers"))
+            .to_owned();
+        let mut officers = Vec::new();
+        for row in conn.query_all(backend.build(&select)).await? {
+            officers.push((row.try_get("", "id")?, row.try_get("", "vrchat_name")?));
+        }
+
+        // The hosts that are already in the event_hosts table, so they aren't added twice
+        let select = Query::select()
+            .columns([Alias::new("event_id"), Alias::new("officer_id")])
+            .from(Alias::new("event_hosts"))
+            .to_owned();
+        let mut existing: Vec<(i32, u64)> = Vec::new();
+        for row in conn.query_all(backend.build(&select)).await? {
+            existing.push((row.try_get("", "event_id")?, row.try_get("", "officer_id")?));
+        }
+
+        // Move the hosts of every event into the event_hosts table
+        let select = Query::select()
+            .columns([Alias::new("id"), hosts_column()])
+            .from(Alias::new("events"))
+            .to_owned();
+        for row in conn.query_all(backend.build(&select)).await? {
+            let event_id: i32 = row.try_get("", "id")?;
+            let hosts: String = row.try_get("", "hosts")?;
+            let (ids, unknown) = parse_hosts(&hosts, &officers);
+            if !unknown.is_empty() {
+                println!("Couldn't find the hosts {:?} of event {}, they are left out", unknown, event_id);
+            }
+
+            for id in ids.into_iter().filter(|id| !existing.contains(&(event_id, *id))) {
+                let insert = Query::insert()
+                    .into_table(Alias::new("event_hosts"))
+                    .columns([Alias::new("event_id"), Alias::new("officer_id")])
+                    .values_panic([event_id.into(), id.into()])
+                    .to_owned();
+                conn.execute(backend.build(&insert)).await?;
+            }
+        }
+
+        manager.alter_table(
+            Table::alter()
+                .table(Alias::new("events"))
+                .drop_column(hosts_column())
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(Alias::new("events"))
+                .add_column(ColumnDef::new(hosts_column()).text().not_null())
+                .to_owned()
+        ).await?;
+
+        // Gather the hosts of every event, in the order they were added
+        let conn = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let select = Query::select()
+            .columns([Alias::new("event_id"), Alias::new("officer_id")])
+            .from(Alias::new("event_hosts"))
+            .order_by(Alias::new("id"), Order::Asc)
+            .to_owned();
+        let mut event_hosts: HashMap<i32, Vec<u64>> = HashMap::new();
+        for row in conn.query_all(backend.build(&select)).await? {
+            let event_id: i32 = row.try_get("", "event_id")?;
+            event_hosts.entry(event_id).or_default().push(row.try_get("", "officer_id")?);
+        }
+
+        // Fill the free text hosts back in as mentions
+        for (event_id, hosts) in event_hosts {
+            let update = Query::update()
+                .table(Alias::new("events"))
+                .value(hosts_column(), join_hosts(&hosts).into())
+                .and_where(Expr::col(Alias::new("id")).eq(event_id))
+                .to_owned();
+            conn.execute(backend.build(&update)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn officer(id: u64, vrchat_name: &str) -> Officer {
+        (id, vrchat_name.to_owned())
+    }
+
+    #[test]
+    fn test_parse_hosts() {
+        let officers = vec![officer(1, "Sgt. Smith"), officer(2, "Jones"), officer(3, "Lee")];
+        let parse = |hosts| parse_hosts(hosts, &officers);
+
+        assert_eq!(parse("<@1>, <@!2>"), (vec![1, 2], vec![]));
+        assert_eq!(parse("sgt. smith and Jones & 3"), (vec![1, 2, 3], vec![]));
+        assert_eq!(parse("<@1> <@3> / Nobody"), (vec![1, 3], vec!["Nobody".to_owned()]));
+        assert_eq!(parse("1, 1, 42"), (vec![1], vec!["42".to_owned()]));
+        assert_eq!(parse(""), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_join_hosts() {
+        assert_eq!(join_hosts(&[1, 2]), "<@1>, <@2>");
+        assert_eq!(join_hosts(&[]), "");
+        assert_eq!(
+            parse_hosts(&join_hosts(&[3, 1]), &[officer(1, "a"), officer(3, "b")]).0,
+            vec![3, 1]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use entity::event;
+use entity::event_template;
+use entity::event_template_host;
+use entity::officer;
+use entity::saved_voice_channel;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220530_000020_add_event_templates"
+    }
+}
+
+/// Add the columns that link an event to the day of a recurring event it was made for, an alter
+/// statement only holds a single change so each column gets its own.
+fn add_event_columns() -> Vec<TableAlterStatement> {
+    vec![
+        Table::alter()
+            .table(event::Entity)
+            .add_column(ColumnDef::new(event::Column::TemplateId).integer())
+            .to_owned(),
+        Table::alter()
+            .table(event::Entity)
+            .add_column(ColumnDef::new(event::Column::OccurrenceDate).date())
+            .to_owned(),
+    ]
+}
+
+/// Drop the columns added by [`add_event_columns`] again.
+fn drop_event_columns() -> Vec<TableAlterStatement> {
+    vec![
+        Table::alter().table(event::Entity).drop_column(event::Column::TemplateId).to_owned(),
+        Table::alter().table(event::Entity).drop_column(event::Column::OccurrenceDate).to_owned(),
+    ]
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(event_template::Entity)
+                .col(ColumnDef::new(event_template::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(event_template::Column::Name).string().not_null())
+                .col(ColumnDef::new(event_template::Column::Description).text())
+                .col(ColumnDef::new(event_template::Column::Recurrence).string().not_null())
+                .col(ColumnDef::new(event_template::Column::FirstDate).date().not_null())
+                .col(ColumnDef::new(event_template::Column::StartTime).time().not_null())
+                .col(ColumnDef::new(event_template::Column::DurationMinutes).integer().not_null())
+                .col(ColumnDef::new(event_template::Column::ChannelId).integer())
+                .col(ColumnDef::new(event_template::Column::CreatedUntil).date())
+                .col(ColumnDef::new(event_template::Column::EndedAt).date_time())
+                .to_owned(),
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event_template-saved_voice_channel")
+                .from(event_template::Entity, event_template::Column::ChannelId)
+                .to(saved_voice_channel::Entity, saved_voice_channel::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_table(
+            Table::create()
+                .table(event_template_host::Entity)
+                .col(ColumnDef::new(event_template_host::Column::Id).integer().not_null().primary_key().auto_increment())
+                .col(ColumnDef::new(event_template_host::Column::TemplateId).integer().not_null())
+                .col(ColumnDef::new(event_template_host::Column::OfficerId).big_unsigned().not_null())
+                .to_owned(),
+        ).await?;
+
+        manager.create_index(
+            Index::create()
+                .name("index-event_template_host-template-officer")
+                .table(event_template_host::Entity)
+                .col(event_template_host::Column::TemplateId)
+                .col(event_template_host::Column::OfficerId)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event_template_host-event_template")
+                .from(event_template_host::Entity, event_template_host::Column::TemplateId)
+                .to(event_template::Entity, event_template::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event_template_host-officer")
+                .from(event_template_host::Entity, event_template_host::Column::OfficerId)
+                .to(officer::Entity, officer::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        for statement in add_event_columns() {
+            manager.alter_table(statement).await?;
+        }
+
+        // Every day of a recurring event only gets a single event
+        manager.create_index(
+            Index::create()
+                .name("index-event-template-occurrence_date")
+                .table(event::Entity)
+                .col(event::Column::TemplateId)
+                .col(event::Column::OccurrenceDate)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("FK-event-event_template")
+                .from(event::Entity, event::Column::TemplateId)
+                .to(event_template::Entity, event_template::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event-event_template")
+                .table(event::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_index(
+            Index::drop()
+                .name("index-event-template-occurrence_date")
+                .table(event::Entity)
+                .to_owned()
+        ).await?;
+
+        for statement in drop_event_columns() {
+            manager.alter_table(statement).await?;
+        }
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event_template_host-officer")
+                .table(event_template_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event_template_host-event_template")
+                .table(event_template_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(event_template_host::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_foreign_key(
+            ForeignKey::drop()
+                .name("FK-event_template-saved_voice_channel")
+                .table(event_template::Entity)
+                .to_owned()
+        ).await?;
+
+        manager.drop_table(
+            sea_query::Table::drop()
+                .table(event_template::Entity)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_columns_statements() {
+        let build = |statements: Vec<TableAlterStatement>| -> Vec<String> {
+            statements.iter().map(|statement| statement.to_string(MysqlQueryBuilder)).collect()
+        };
+
+        let added = build(add_event_columns());
+        assert_eq!(added.len(), 2);
+        assert!(added[0].contains("ADD COLUMN `template_id`"));
+        assert!(added[1].contains("ADD COLUMN `occurrence_date`"));
+
+        let dropped = build(drop_event_columns());
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped[0].contains("DROP COLUMN `template_id`"));
+        assert!(dropped[1].contains("DROP COLUMN `occurrence_date`"));
+    }
+}
@@ -0,0 +1,37 @@
+use entity::patrol_adjustment;
+use sea_schema::migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220601_000021_add_adjustment_seconds"
+    }
+}
+
+#[async_trait::async_trait]
+#[rustfmt::skip]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Removing part of a patrol keeps the patrol and records how much of it doesn't count
+        manager.alter_table(
+            Table::alter()
+                .table(patrol_adjustment::Entity)
+                .add_column(ColumnDef::new(patrol_adjustment::Column::Seconds).big_integer())
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(patrol_adjustment::Entity)
+                .drop_column(patrol_adjustment::Column::Seconds)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
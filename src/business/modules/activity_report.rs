@@ -0,0 +1,100 @@
+use super::{event, loa, member_management, patrol_measure};
+use crate::global::{Error, OfficerCache};
+use entity::sea_orm::DatabaseConnection;
+use poise::serenity_prelude as serenity;
+
+/// Get the date the days given ago.
+pub fn date_from_days(days: i64) -> Result<chrono::NaiveDate, Error> {
+    Ok(chrono::Utc::now()
+        .checked_sub_signed(chrono::Duration::days(days))
+        .ok_or("From date overflow!")?
+        .naive_utc()
+        .date())
+}
+
+/// An officer that doesn't meet the patrol requirements.
+#[derive(Debug, Clone)]
+pub struct InactiveOfficer {
+    pub officer: entity::officer::Model,
+    /// Counted time in seconds
+    pub counted: i64,
+    pub last_patrol: Option<chrono::NaiveDateTime>,
+    pub loa_days: i64,
+}
+
+/// The officers that don't meet the patrol requirements in the last days given.
+#[derive(Debug, Clone)]
+pub struct InactivityReport {
+    pub days: i64,
+    pub required_hours: f64,
+    pub to: chrono::NaiveDateTime,
+    /// The officers with the least time first
+    pub officers: Vec<InactiveOfficer>,
+}
+
+/// The patrol activity of the whole department in the last days given.
+#[derive(Debug, Clone)]
+pub struct ActivitySummary {
+    pub from_date: chrono::NaiveDate,
+    pub to: chrono::NaiveDateTime,
+    pub officer_count: usize,
+    /// The officers that patrolled, with the most time first
+    pub entries: Vec<patrol_measure::LeaderboardEntry>,
+}
+
+/// Find the officers that don't meet the patrol requirements in the last days given.
+///
+/// Everyone that is currently in the LPD and has less counted patrol time than required in the
+/// time period is listed, together with their last patrol.
+/// Days spent on an approved leave of absence lower the requirement by the same share of the time
+/// period, officers on leave for all of it are left out.
+pub async fn inactivity_report(
+    conn: &DatabaseConnection,
+    officer_cache: &OfficerCache,
+    days: i64,
+    required_hours: f64,
+) -> Result<InactivityReport, Error> {
+    let required_seconds = (required_hours * 3600.0) as i64;
+    let from_date = date_from_days(days)?;
+    let from = from_date.and_hms(0, 0, 0);
+    let to = chrono::Utc::now().naive_utc();
+    let window_days = to.date().signed_duration_since(from_date).num_days() + 1;
+
+    // Get the approved leaves of absence in the window, their days don't count towards it
+    let loas = loa::get_loas(conn, None, from_date, to.date(), false).await?;
+
+    // Find everyone under the required time
+    let mut officers = Vec::new();
+    for officer in member_management::get_active_officers(officer_cache).await {
+        let user_id = serenity::UserId(officer.id);
+        let officer_loas: Vec<_> =
+            loas.iter().filter(|loa| loa.officer_id == officer.id).cloned().collect();
+        let loa_days = loa::count_loa_days(&officer_loas, from_date, to.date());
+        let required = match loa::scale_requirement(required_seconds, window_days, loa_days) {
+            Some(required) => required,
+            None => continue,
+        };
+
+        let counted = event::get_counted_time(conn, from, to, user_id).await?;
+        if counted < required {
+            let last_patrol = patrol_measure::get_last_patrol_end(conn, user_id).await?;
+            officers.push(InactiveOfficer { officer, counted, last_patrol, loa_days });
+        }
+    }
+    officers.sort_by_key(|item| (item.counted, item.officer.started_monitoring));
+
+    Ok(InactivityReport { days, required_hours, to, officers })
+}
+
+/// Sum up the patrol activity of the whole department in the last days given.
+pub async fn activity_summary_report(
+    conn: &DatabaseConnection,
+    officer_cache: &OfficerCache,
+    days: i64,
+) -> Result<ActivitySummary, Error> {
+    let from_date = date_from_days(days)?;
+    let to = chrono::Utc::now().naive_utc();
+    let entries = patrol_measure::get_leaderboard(conn, from_date.and_hms(0, 0, 0), to).await?;
+    let officer_count = member_management::get_active_officers(officer_cache).await.len();
+    Ok(ActivitySummary { from_date, to, officer_count, entries })
+}
@@ -0,0 +1,264 @@
+use entity::notification;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::QueryFilter;
+
+use super::event;
+use super::loa;
+use super::member_management;
+use crate::config::CONFIG;
+use crate::global::{Error, OfficerCache};
+use poise::serenity_prelude as serenity;
+
+/// A period officers have to meet the activity requirements in, both days are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    pub start: chrono::NaiveDate,
+    pub end: chrono::NaiveDate,
+}
+
+/// Get the requirement period a day is in.
+///
+/// Periods are window_days long and follow each other from the anchor on, in both directions.
+pub fn get_period(anchor: chrono::NaiveDate, window_days: i64, day: chrono::NaiveDate) -> Period {
+    let offset = day.signed_duration_since(anchor).num_days().div_euclid(window_days);
+    let start = anchor + chrono::Duration::days(offset * window_days);
+    Period { start, end: start + chrono::Duration::days(window_days - 1) }
+}
+
+/// The notifications officers can get about the activity requirements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    /// A warning the given number of days before the period is over
+    Warning(i64),
+    /// The period is over and the requirement wasn't met
+    Final,
+}
+
+impl Notice {
+    /// The kind the notification is stored as in the database.
+    fn kind(&self) -> String {
+        match self {
+            Notice::Warning(days) => format!("activity_warning_{}", days),
+            Notice::Final => "activity_final".to_owned(),
+        }
+    }
+}
+
+/// Get the warning that is due with the days left in a period, which is the one for the fewest
+/// days that aren't less than the days left.
+pub fn get_warning_days(warning_days: &[i64], days_left: i64) -> Option<i64> {
+    warning_days.iter().filter(|days| days_left <= **days).min().copied()
+}
+
+/// Check if an officer is on track to fall below the requirement at the end of a period.
+///
+/// The time counted so far is extended over the whole period at the same pace, elapsed_days
+/// includes the current day.
+pub fn is_trending_below(counted: i64, required: i64, elapsed_days: i64, window_days: i64) -> bool {
+    counted < required && counted * window_days < required * elapsed_days
+}
+
+/// How many notifications were sent by [`send_activity_warnings`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WarningSummary {
+    pub sent: usize,
+    /// Notifications that were sent as a ping in the warning channel as the DM couldn't be sent
+    pub pinged: usize,
+    pub failed: usize,
+}
+
+/// Get the patrol time an officer counted in a period up to the time given and the time they need.
+///
+/// Days the officer spent on an approved leave of absence lower the time they need, returns
+/// [`None`] if nothing is needed because they were on leave for the whole period.
+async fn get_requirement_status(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    period: Period,
+    until: chrono::NaiveDateTime,
+) -> Result<Option<(i64, i64)>, Error> {
+    let loas = loa::get_loas(conn, Some(user_id), period.start, period.end, false).await?;
+    let loa_days = loa::count_loa_days(&loas, period.start, period.end);
+    let required_seconds = (CONFIG.activity.required_hours * 3600.0) as i64;
+    let required =
+        match loa::scale_requirement(required_seconds, CONFIG.activity.window_days, loa_days) {
+            Some(required) => required,
+            None => return Ok(None),
+        };
+
+    let from = period.start.and_hms(0, 0, 0);
+    let to = until.min(period.end.and_hms(23, 59, 59));
+    let counted = event::get_counted_time(conn, from, to, user_id).await?;
+    Ok(Some((counted, required)))
+}
+
+fn hours(seconds: i64) -> String {
+    format!("{:.1}", seconds as f64 / 3600.0)
+}
+
+/// Send a notification to an officer unless they already got it for the period, recording it.
+///
+/// The notification is sent as a DM, if that fails the officer is pinged with it in the warning
+/// channel instead. Returns whether it had to be sent through the channel, or [`None`] if it had
+/// already been sent before.
+async fn notify(
+    conn: &DatabaseConnection,
+    http: &serenity::Http,
+    user_id: serenity::UserId,
+    notice: Notice,
+    period: Period,
+    message: &str,
+) -> Result<Option<bool>, Error> {
+    let sent_before = notification::Entity::find()
+        .filter(notification::Column::OfficerId.eq(user_id.0))
+        .filter(notification::Column::Kind.eq(notice.kind()))
+        .filter(notification::Column::PeriodStart.eq(period.start))
+        .one(conn)
+        .await?;
+    if sent_before.is_some() {
+        return Ok(None);
+    }
+
+    // Try sending a DM first
+    let dm_result = match user_id.create_dm_channel(http).await {
+        Ok(channel) => channel.say(http, message).await.map(|_| ()),
+        Err(err) => Err(err),
+    };
+    let via_dm = match dm_result {
+        Ok(()) => true,
+        Err(err) => {
+            println!(
+                "Couldn't DM {} their activity notice, pinging them instead: {}",
+                user_id, err
+            );
+            serenity::ChannelId(CONFIG.activity.warning_channel)
+                .say(http, format!("<@{}> {}", user_id.0, message))
+                .await?;
+            false
+        }
+    };
+
+    use entity::sea_orm::entity::*;
+    let active_model = notification::ActiveModel {
+        officer_id: Set(user_id.0),
+        kind: Set(notice.kind()),
+        period_start: Set(period.start),
+        sent_at: Set(chrono::Utc::now().naive_utc()),
+        via_dm: Set(via_dm),
+        ..Default::default()
+    };
+    active_model.insert(conn).await?;
+
+    Ok(Some(!via_dm))
+}
+
+/// Warn the officers that are trending below the activity requirements of the current period and
+/// send a final notice to the ones that didn't meet them in the period that just ended.
+///
+/// Officers that started being monitored after a period started aren't held to it. Each
+/// notification is only sent once per officer and period, so this can be run as often as needed.
+pub async fn send_activity_warnings(
+    conn: &DatabaseConnection,
+    http: &serenity::Http,
+    officer_cache: &OfficerCache,
+    now: chrono::NaiveDateTime,
+) -> Result<WarningSummary, Error> {
+    let window_days = CONFIG.activity.window_days;
+    let today = now.date();
+    let period = get_period(CONFIG.activity.period_anchor, window_days, today);
+    let previous = get_period(CONFIG.activity.period_anchor, window_days, period.start.pred());
+
+    // Find out which notices are due today
+    let days_left = period.end.signed_duration_since(today).num_days();
+    let elapsed_days = today.signed_duration_since(period.start).num_days() + 1;
+    let warning = get_warning_days(&CONFIG.activity.warning_days, days_left);
+    let final_due =
+        today.signed_duration_since(previous.end).num_days() <= CONFIG.activity.final_notice_days;
+
+    let mut summary = WarningSummary::default();
+    for officer in member_management::get_active_officers(officer_cache).await {
+        let user_id = serenity::UserId(officer.id);
+        let mut notices = Vec::new();
+
+        if let Some(days) = warning.filter(|_| officer.started_monitoring.date() <= period.start) {
+            if let Some((counted, required)) =
+                get_requirement_status(conn, user_id, period, now).await?
+            {
+                if is_trending_below(counted, required, elapsed_days, window_days) {
+                    let message = format!(
+                        "You have {} of the {} hours of patrol time you need by {}, there are {} days left to meet the activity requirement.",
+                        hours(counted), hours(required), period.end, days_left + 1
+                    );
+                    notices.push((Notice::Warning(days), period, message));
+                }
+            }
+        }
+
+        if final_due && officer.started_monitoring.date() <= previous.start {
+            let until = previous.end.and_hms(23, 59, 59);
+            if let Some((counted, required)) =
+                get_requirement_status(conn, user_id, previous, until).await?
+            {
+                if counted < required {
+                    let message = format!(
+                        "The activity period from {} to {} is over and you had {} of the {} hours of patrol time needed.",
+                        previous.start, previous.end, hours(counted), hours(required)
+                    );
+                    notices.push((Notice::Final, previous, message));
+                }
+            }
+        }
+
+        for (notice, period, message) in notices {
+            match notify(conn, http, user_id, notice, period, &message).await {
+                Ok(Some(pinged)) => {
+                    summary.sent += 1;
+                    summary.pinged += usize::from(pinged);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    println!("Failed sending an activity notice to {}: {}", user_id, err);
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_period() {
+        let anchor = chrono::NaiveDate::from_ymd(2022, 5, 2);
+        let period = |start: (u32, u32), end: (u32, u32)| Period {
+            start: chrono::NaiveDate::from_ymd(2022, start.0, start.1),
+            end: chrono::NaiveDate::from_ymd(2022, end.0, end.1),
+        };
+        let day = |month, day| chrono::NaiveDate::from_ymd(2022, month, day);
+        assert_eq!(get_period(anchor, 28, day(5, 2)), period((5, 2), (5, 29)));
+        assert_eq!(get_period(anchor, 28, day(5, 29)), period((5, 2), (5, 29)));
+        assert_eq!(get_period(anchor, 28, day(5, 30)), period((5, 30), (6, 26)));
+        assert_eq!(get_period(anchor, 28, day(5, 1)), period((4, 4), (5, 1)));
+    }
+
+    #[test]
+    fn test_get_warning_days() {
+        assert_eq!(get_warning_days(&[7, 2], 10), None);
+        assert_eq!(get_warning_days(&[7, 2], 7), Some(7));
+        assert_eq!(get_warning_days(&[7, 2], 3), Some(7));
+        assert_eq!(get_warning_days(&[7, 2], 0), Some(2));
+    }
+
+    #[test]
+    fn test_is_trending_below() {
+        // 4 hours needed in 28 days
+        let required = 4 * 3600;
+        assert!(is_trending_below(3600, required, 14, 28));
+        assert!(!is_trending_below(2 * 3600, required, 14, 28));
+        assert!(!is_trending_below(required, required, 28, 28));
+    }
+}
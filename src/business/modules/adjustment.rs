@@ -0,0 +1,367 @@
+use entity::event;
+use entity::patrol;
+use entity::patrol_adjustment;
+use entity::sea_orm;
+use entity::sea_orm::sea_query::Expr;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::ConnectionTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::FromQueryResult;
+use entity::sea_orm::JoinType;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+use entity::sea_orm::QuerySelect;
+use entity::sea_orm::RelationTrait;
+use entity::sea_orm::TransactionTrait;
+
+use super::status_board::display_elapsed;
+use crate::global::Error;
+use poise::serenity_prelude as serenity;
+use std::collections::{HashMap, HashSet};
+
+const ADD_TIME: &str = "add_time";
+const REMOVE_TIME: &str = "remove_time";
+const UNDO: &str = "undo";
+
+/// Record an adjustment in the audit table.
+async fn record_adjustment<C: ConnectionTrait>(
+    conn: &C,
+    patrol: &patrol::Model,
+    supervisor: serenity::UserId,
+    action: &str,
+    reason: String,
+    undoes: Option<i32>,
+    seconds: Option<i64>,
+) -> Result<patrol_adjustment::Model, Error> {
+    use entity::sea_orm::entity::*;
+    let active_model = patrol_adjustment::ActiveModel {
+        patrol_id: Set(patrol.id),
+        officer_id: Set(patrol.officer_id),
+        supervisor_id: Set(supervisor.0),
+        action: Set(action.to_owned()),
+        reason: Set(reason),
+        undoes: Set(undoes),
+        seconds: Set(seconds),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    };
+    Ok(active_model.insert(conn).await?)
+}
+
+/// Set whether a patrol is ignored and record why in the audit table.
+async fn set_ignored(
+    conn: &DatabaseConnection,
+    patrol: patrol::Model,
+    ignored: bool,
+    supervisor: serenity::UserId,
+    action: &str,
+    reason: String,
+    undoes: Option<i32>,
+) -> Result<patrol_adjustment::Model, Error> {
+    use entity::sea_orm::entity::*;
+    let txn = conn.begin().await?;
+    let mut active_model: patrol::ActiveModel = patrol.into();
+    active_model.ignored = Set(ignored);
+    let patrol = active_model.update(&txn).await?;
+    let adjustment =
+        record_adjustment(&txn, &patrol, supervisor, action, reason, undoes, None).await?;
+    txn.commit().await?;
+    Ok(adjustment)
+}
+
+/// Add patrol time to an officer by hand, like when the bot was down or they patrolled without
+/// being in voice.
+///
+/// This creates a patrol without any voice channels that is marked with the supervisor that
+/// entered it.
+pub async fn add_time(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    event_id: Option<i32>,
+    supervisor: serenity::UserId,
+    reason: String,
+) -> Result<(patrol::Model, patrol_adjustment::Model), Error> {
+    if end <= start {
+        return Err("The added time has to end after it starts.".into());
+    }
+    if let Some(event_id) = event_id {
+        event::Entity::find_by_id(event_id)
+            .one(conn)
+            .await?
+            .ok_or(format!("There is no event with the id {}.", event_id))?;
+    }
+
+    use entity::sea_orm::entity::*;
+    let active_model = patrol::ActiveModel {
+        officer_id: Set(user_id.0),
+        main_channel_id: Set(None),
+        start: Set(start),
+        end: Set(end),
+        event_id: Set(event_id),
+        ignored: Set(false),
+        manual_by: Set(Some(supervisor.0)),
+        reason: Set(Some(reason.clone())),
+        ..Default::default()
+    };
+
+    let txn = conn.begin().await?;
+    let patrol = active_model.insert(&txn).await?;
+    let adjustment =
+        record_adjustment(&txn, &patrol, supervisor, ADD_TIME, reason, None, None).await?;
+    txn.commit().await?;
+
+    Ok((patrol, adjustment))
+}
+
+/// The time removed from patrols by hand per officer, as summed up for the patrol time.
+#[derive(Debug, Clone, FromQueryResult)]
+struct RemovedTime {
+    officer_id: u64,
+    /// Removed time in seconds
+    removed_time: i64,
+}
+
+/// Check that the seconds to remove from a patrol are more than 0 and no more than what is left
+/// of it after the time already removed.
+fn check_partial_removal(seconds: i64, patrol_seconds: i64, removed: i64) -> Result<(), Error> {
+    if seconds <= 0 {
+        return Err("The time to remove has to be more than 0.".into());
+    }
+    if seconds > patrol_seconds - removed {
+        return Err(format!(
+            "Only {} seconds of the patrol are left to remove.",
+            (patrol_seconds - removed).max(0)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Add up the time removed from a patrol by the adjustments made to it, leaving out the removals
+/// that were undone.
+fn sum_removed_seconds(adjustments: &[patrol_adjustment::Model]) -> i64 {
+    let undone: HashSet<_> =
+        adjustments.iter().filter_map(|adjustment| adjustment.undoes).collect();
+    adjustments
+        .iter()
+        .filter(|adjustment| adjustment.action == REMOVE_TIME && !undone.contains(&adjustment.id))
+        .filter_map(|adjustment| adjustment.seconds)
+        .sum()
+}
+
+/// Get the time removed from part of patrols by hand per officer, for the patrols that ended
+/// between the from and to times, both of which are inclusive.
+///
+/// Removals that were undone and patrols that don't count as a whole are left out.
+pub async fn get_removed_time(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    user_id: Option<serenity::UserId>,
+) -> Result<HashMap<u64, i64>, Error> {
+    let mut query = patrol_adjustment::Entity::find()
+        .select_only()
+        .column(patrol_adjustment::Column::OfficerId)
+        .column_as(
+            Expr::cust("CAST(SUM(`patrol_adjustments`.`seconds`) AS SIGNED)"),
+            "removed_time",
+        )
+        .join(JoinType::InnerJoin, patrol_adjustment::Relation::Patrol.def())
+        .filter(patrol_adjustment::Column::Action.eq(REMOVE_TIME))
+        .filter(patrol_adjustment::Column::Seconds.is_not_null())
+        .filter(Expr::cust(
+            "NOT EXISTS (SELECT 1 FROM `patrol_adjustments` AS `u` WHERE `u`.`undoes` = `patrol_adjustments`.`id`)",
+        ))
+        .filter(patrol::Column::Ignored.eq(false))
+        .filter(patrol::Column::End.between(from, to));
+    if let Some(user_id) = user_id {
+        query = query.filter(patrol_adjustment::Column::OfficerId.eq(user_id.0));
+    }
+    Ok(query
+        .group_by(patrol_adjustment::Column::OfficerId)
+        .into_model::<RemovedTime>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|row| (row.officer_id, row.removed_time))
+        .collect())
+}
+
+/// Take time out of an officer's patrol time.
+///
+/// Without the seconds given the whole patrol is kept in the database but ignored, otherwise the
+/// patrol stays as it is and the adjustment records how much of it doesn't count.
+pub async fn remove_time(
+    conn: &DatabaseConnection,
+    patrol_id: i32,
+    seconds: Option<i64>,
+    supervisor: serenity::UserId,
+    reason: String,
+) -> Result<patrol_adjustment::Model, Error> {
+    let patrol = patrol::Entity::find_by_id(patrol_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no patrol with the id {}.", patrol_id))?;
+    if patrol.ignored {
+        return Err(format!("The patrol {} already doesn't count.", patrol_id).into());
+    }
+
+    let seconds = match seconds {
+        Some(seconds) => seconds,
+        None => {
+            return set_ignored(conn, patrol, true, supervisor, REMOVE_TIME, reason, None).await
+        }
+    };
+    let adjustments = patrol_adjustment::Entity::find()
+        .filter(patrol_adjustment::Column::PatrolId.eq(patrol.id))
+        .all(conn)
+        .await?;
+    let patrol_seconds = patrol.end.signed_duration_since(patrol.start).num_seconds();
+    check_partial_removal(seconds, patrol_seconds, sum_removed_seconds(&adjustments))?;
+    record_adjustment(conn, &patrol, supervisor, REMOVE_TIME, reason, None, Some(seconds)).await
+}
+
+/// Undo an adjustment, which is recorded in the audit table as an adjustment of its own.
+///
+/// Added time is ignored again and removed time counts again, undoing an undo isn't possible.
+pub async fn undo_adjustment(
+    conn: &DatabaseConnection,
+    adjustment_id: i32,
+    supervisor: serenity::UserId,
+    reason: String,
+) -> Result<patrol_adjustment::Model, Error> {
+    let adjustment = patrol_adjustment::Entity::find_by_id(adjustment_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no adjustment with the id {}.", adjustment_id))?;
+    let undone_before = patrol_adjustment::Entity::find()
+        .filter(patrol_adjustment::Column::Undoes.eq(adjustment_id))
+        .one(conn)
+        .await?;
+    if undone_before.is_some() {
+        return Err(format!("The adjustment {} has already been undone.", adjustment_id).into());
+    }
+
+    let ignored = match adjustment.action.as_str() {
+        ADD_TIME => true,
+        REMOVE_TIME => false,
+        _ => return Err("Only added or removed time can be undone.".into()),
+    };
+    let patrol = patrol::Entity::find_by_id(adjustment.patrol_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no patrol with the id {}.", adjustment.patrol_id))?;
+
+    // Removing part of a patrol didn't change the patrol, so recording the undo is enough
+    if adjustment.seconds.is_some() {
+        let undoes = Some(adjustment_id);
+        return record_adjustment(conn, &patrol, supervisor, UNDO, reason, undoes, None).await;
+    }
+    set_ignored(conn, patrol, ignored, supervisor, UNDO, reason, Some(adjustment_id)).await
+}
+
+/// Get the adjustments made to the patrol time of an officer, newest first.
+pub async fn get_adjustments(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+) -> Result<Vec<patrol_adjustment::Model>, Error> {
+    Ok(patrol_adjustment::Entity::find()
+        .filter(patrol_adjustment::Column::OfficerId.eq(user_id.0))
+        .order_by_desc(patrol_adjustment::Column::CreatedAt)
+        .order_by_desc(patrol_adjustment::Column::Id)
+        .all(conn)
+        .await?)
+}
+
+/// Make a single line string that represents an adjustment.
+pub fn display_adjustment(adjustment: &patrol_adjustment::Model) -> String {
+    let action = match adjustment.undoes {
+        Some(undoes) => format!("undo of #{}", undoes),
+        None => match adjustment.seconds {
+            Some(seconds) => format!("remove {}", display_elapsed(seconds)),
+            None => adjustment.action.replace('_', " "),
+        },
+    };
+    format!(
+        "#{} {} on patrol #{} by <@{}> at {}: {}",
+        adjustment.id,
+        action,
+        adjustment.patrol_id,
+        adjustment.supervisor_id,
+        adjustment.created_at.format("%Y-%m-%d %H:%M"),
+        adjustment.reason
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_adjustment() {
+        let mut adjustment = patrol_adjustment::Model {
+            id: 2,
+            patrol_id: 5,
+            officer_id: 1,
+            supervisor_id: 3,
+            action: ADD_TIME.to_owned(),
+            reason: "Bot was down".to_owned(),
+            undoes: None,
+            seconds: None,
+            created_at: chrono::NaiveDate::from_ymd(2022, 5, 1).and_hms(12, 30, 0),
+        };
+        assert_eq!(
+            display_adjustment(&adjustment),
+            "#2 add time on patrol #5 by <@3> at 2022-05-01 12:30: Bot was down"
+        );
+
+        adjustment.action = UNDO.to_owned();
+        adjustment.undoes = Some(1);
+        assert_eq!(
+            display_adjustment(&adjustment),
+            "#2 undo of #1 on patrol #5 by <@3> at 2022-05-01 12:30: Bot was down"
+        );
+    }
+
+    fn adjustment(
+        id: i32,
+        action: &str,
+        undoes: Option<i32>,
+        seconds: Option<i64>,
+    ) -> patrol_adjustment::Model {
+        patrol_adjustment::Model {
+            id,
+            patrol_id: 5,
+            officer_id: 1,
+            supervisor_id: 3,
+            action: action.to_owned(),
+            reason: String::new(),
+            undoes,
+            seconds,
+            created_at: chrono::NaiveDate::from_ymd(2022, 5, 1).and_hms(12, 30, 0),
+        }
+    }
+
+    #[test]
+    fn test_partial_removal() {
+        // 30 minutes off a 3 hour patrol, then an hour that was undone again
+        let adjustments = vec![
+            adjustment(1, REMOVE_TIME, None, Some(1800)),
+            adjustment(2, REMOVE_TIME, None, Some(3600)),
+            adjustment(3, UNDO, Some(2), None),
+        ];
+        let removed = sum_removed_seconds(&adjustments);
+        assert_eq!(removed, 1800);
+
+        assert!(check_partial_removal(9000, 10800, removed).is_ok());
+        assert!(check_partial_removal(9001, 10800, removed).is_err());
+        assert!(check_partial_removal(0, 10800, removed).is_err());
+        assert_eq!(
+            display_adjustment(&adjustments[0]),
+            "#1 remove 0h 30m on patrol #5 by <@3> at 2022-05-01 12:30: "
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use entity::patrol;
+use entity::sea_orm::sea_query::Expr;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::QueryFilter;
+use entity::time_claim;
+
+use super::adjustment;
+use crate::business::has_leadership_role;
+use crate::config::CONFIG;
+use crate::global::{Data, Error};
+use poise::serenity_prelude as serenity;
+use std::future::Future;
+
+const PENDING: &str = "pending";
+const APPROVED: &str = "approved";
+const DENIED: &str = "denied";
+
+/// File a claim for patrol time that the bot missed, it counts once leadership approves it.
+///
+/// The claim has to be in the past, no older than the settings allow, long enough to count as a
+/// patrol and it can't overlap any of the officer's patrols.
+pub async fn file_claim(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    reason: String,
+) -> Result<time_claim::Model, Error> {
+    let now = chrono::Utc::now().naive_utc();
+    if end <= start {
+        return Err("The claimed time has to end after it starts.".into());
+    }
+    if end > now {
+        return Err("Time can only be claimed once it has passed.".into());
+    }
+    if now.signed_duration_since(start).num_days() > CONFIG.claims.max_age_days {
+        return Err(format!(
+            "Time can only be claimed up to {} days back.",
+            CONFIG.claims.max_age_days
+        )
+        .into());
+    }
+    if end.signed_duration_since(start).num_seconds() < CONFIG.patrol_time.min_patrol_seconds {
+        return Err("The claimed time is too short to count as a patrol.".into());
+    }
+
+    // Time the bot already counted can't be claimed again
+    let overlapping = patrol::Entity::find()
+        .filter(patrol::Column::OfficerId.eq(user_id.0))
+        .filter(patrol::Column::Ignored.eq(false))
+        .filter(patrol::Column::Start.lt(end))
+        .filter(patrol::Column::End.gt(start))
+        .one(conn)
+        .await?;
+    if let Some(patrol) = overlapping {
+        return Err(format!(
+            "The claimed time overlaps with patrol #{} from {} to {}.",
+            patrol.id, patrol.start, patrol.end
+        )
+        .into());
+    }
+
+    use entity::sea_orm::entity::*;
+    let active_model = time_claim::ActiveModel {
+        officer_id: Set(user_id.0),
+        start: Set(start),
+        end: Set(end),
+        reason: Set(reason),
+        status: Set(PENDING.to_owned()),
+        reviewed_by: Set(None),
+        review_reason: Set(None),
+        patrol_id: Set(None),
+        review_message_id: Set(None),
+        created_at: Set(now),
+        ..Default::default()
+    };
+    Ok(active_model.insert(conn).await?)
+}
+
+/// Store which message in the review channel belongs to a claim.
+pub async fn set_review_message(
+    conn: &DatabaseConnection,
+    claim: time_claim::Model,
+    message_id: serenity::MessageId,
+) -> Result<time_claim::Model, Error> {
+    use entity::sea_orm::entity::*;
+    let mut active_model: time_claim::ActiveModel = claim.into();
+    active_model.review_message_id = Set(Some(message_id.0));
+    Ok(active_model.update(conn).await?)
+}
+
+/// Mark a pending claim as reviewed, this fails if someone else reviewed it first.
+async fn review_claim(
+    conn: &DatabaseConnection,
+    claim_id: i32,
+    status: &str,
+    reviewer: serenity::UserId,
+    review_reason: Option<String>,
+) -> Result<time_claim::Model, Error> {
+    let result = time_claim::Entity::update_many()
+        .col_expr(time_claim::Column::Status, Expr::value(status))
+        .col_expr(time_claim::Column::ReviewedBy, Expr::value(reviewer.0))
+        .col_expr(time_claim::Column::ReviewReason, Expr::value(review_reason))
+        .filter(time_claim::Column::Id.eq(claim_id))
+        .filter(time_claim::Column::Status.eq(PENDING))
+        .exec(conn)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(
+            format!("Claim #{} doesn't exist or has already been reviewed.", claim_id).into()
+        );
+    }
+
+    Ok(time_claim::Entity::find_by_id(claim_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no claim with the id {}.", claim_id))?)
+}
+
+/// Approve a pending claim, which adds the claimed time as a patrol entered by the reviewer.
+pub async fn approve_claim(
+    conn: &DatabaseConnection,
+    claim_id: i32,
+    reviewer: serenity::UserId,
+) -> Result<time_claim::Model, Error> {
+    let claim = review_claim(conn, claim_id, APPROVED, reviewer, None).await?;
+
+    let reason = format!("Claim #{}: {}", claim.id, claim.reason);
+    let user_id = serenity::UserId(claim.officer_id);
+    let patrol =
+        match adjustment::add_time(conn, user_id, claim.start, claim.end, None, reviewer, reason)
+            .await
+        {
+            Ok((patrol, _)) => patrol,
+            Err(err) => {
+                // Put the claim back up for review so it isn't lost
+                use entity::sea_orm::entity::*;
+                let mut active_model: time_claim::ActiveModel = claim.into();
+                active_model.status = Set(PENDING.to_owned());
+                active_model.reviewed_by = Set(None);
+                active_model.review_reason = Set(None);
+                active_model.update(conn).await?;
+                return Err(err);
+            }
+        };
+
+    use entity::sea_orm::entity::*;
+    let mut active_model: time_claim::ActiveModel = claim.into();
+    active_model.patrol_id = Set(Some(patrol.id));
+    Ok(active_model.update(conn).await?)
+}
+
+/// Deny a pending claim for the reason given.
+pub async fn deny_claim(
+    conn: &DatabaseConnection,
+    claim_id: i32,
+    reviewer: serenity::UserId,
+    reason: String,
+) -> Result<time_claim::Model, Error> {
+    review_claim(conn, claim_id, DENIED, reviewer, Some(reason)).await
+}
+
+/// Make a multi line string that represents a claim, including how it was reviewed.
+pub fn display_claim(claim: &time_claim::Model) -> String {
+    let mut result = format!(
+        "Claim #{} by <@{}> from {} to {}: {}",
+        claim.id, claim.officer_id, claim.start, claim.end, claim.reason
+    );
+    if let Some(reviewer) = claim.reviewed_by {
+        result += &format!("\n{} by <@{}>", claim.status.to_uppercase(), reviewer);
+        if let Some(review_reason) = &claim.review_reason {
+            result += &format!(": {}", review_reason);
+        }
+    }
+    result
+}
+
+/// What a button or form in the review channel does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewAction {
+    Approve,
+    Deny,
+    /// The form with the reason for denying a claim was sent
+    DenyReason,
+}
+
+impl ReviewAction {
+    fn custom_id(&self, claim_id: i32) -> String {
+        match self {
+            ReviewAction::Approve => format!("claim-approve-{}", claim_id),
+            ReviewAction::Deny => format!("claim-deny-{}", claim_id),
+            ReviewAction::DenyReason => format!("claim-deny_reason-{}", claim_id),
+        }
+    }
+
+    /// Get the action and claim id from the custom id of a button or form.
+    fn parse(custom_id: &str) -> Option<(ReviewAction, i32)> {
+        let mut parts = custom_id.splitn(3, '-');
+        if parts.next()? != "claim" {
+            return None;
+        }
+        let action = match parts.next()? {
+            "approve" => ReviewAction::Approve,
+            "deny" => ReviewAction::Deny,
+            "deny_reason" => ReviewAction::DenyReason,
+            _ => return None,
+        };
+        Some((action, parts.next()?.parse().ok()?))
+    }
+}
+
+/// Send the message for a claim in the review channel, with buttons to approve or deny it.
+async fn send_review_message(
+    http: &serenity::Http,
+    claim: &time_claim::Model,
+) -> Result<serenity::Message, Error> {
+    let message = serenity::ChannelId(CONFIG.claims.review_channel)
+        .send_message(http, |m| {
+            m.content(display_claim(claim)).components(|c| {
+                c.create_action_row(|row| {
+                    row.create_button(|b| {
+                        b.custom_id(ReviewAction::Approve.custom_id(claim.id))
+                            .label("Approve")
+                            .style(serenity::ButtonStyle::Success)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(ReviewAction::Deny.custom_id(claim.id))
+                            .label("Deny")
+                            .style(serenity::ButtonStyle::Danger)
+                    })
+                })
+            })
+        })
+        .await?;
+    Ok(message)
+}
+
+/// Remove a claim that never made it to the review channel.
+async fn withdraw_claim(conn: &DatabaseConnection, claim_id: i32) -> Result<(), Error> {
+    time_claim::Entity::delete_by_id(claim_id).exec(conn).await?;
+    Ok(())
+}
+
+/// Post a claim for review with the first function, withdrawing it with the second one if that
+/// fails so no pending claim is left without a message to review it with.
+async fn post_or_withdraw<T, P, PF, W, WF>(claim_id: i32, post: P, withdraw: W) -> Result<T, Error>
+where
+    P: FnOnce() -> PF,
+    PF: Future<Output = Result<T, Error>>,
+    W: FnOnce() -> WF,
+    WF: Future<Output = Result<(), Error>>,
+{
+    let err = match post().await {
+        Ok(posted) => return Ok(posted),
+        Err(err) => err,
+    };
+    if let Err(withdraw_err) = withdraw().await {
+        println!("Couldn't withdraw claim #{} that failed to post: {}", claim_id, withdraw_err);
+    }
+    Err(format!("Claim #{} couldn't be posted for review and was withdrawn: {}", claim_id, err)
+        .into())
+}
+
+/// Post a claim in the review channel with buttons to approve or deny it, the claim is withdrawn
+/// if that fails.
+///
+/// The buttons keep working after a restart as they are handled by [`event_listener`].
+pub async fn post_for_review(
+    conn: &DatabaseConnection,
+    http: &serenity::Http,
+    claim: time_claim::Model,
+) -> Result<time_claim::Model, Error> {
+    let message = post_or_withdraw(
+        claim.id,
+        || send_review_message(http, &claim),
+        || withdraw_claim(conn, claim.id),
+    )
+    .await?;
+    set_review_message(conn, claim, message.id).await
+}
+
+/// Let the officer know how their claim was reviewed, failing to do so is only logged.
+async fn notify_officer(http: &serenity::Http, claim: &time_claim::Model) {
+    let message = match claim.review_reason.as_ref() {
+        Some(reason) => format!(
+            "Your claim #{} for {} to {} was {}: {}",
+            claim.id, claim.start, claim.end, claim.status, reason
+        ),
+        None => format!(
+            "Your claim #{} for {} to {} was {}.",
+            claim.id, claim.start, claim.end, claim.status
+        ),
+    };
+    let user_id = serenity::UserId(claim.officer_id);
+    let result = match user_id.create_dm_channel(http).await {
+        Ok(channel) => channel.say(http, message).await.map(|_| ()),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        println!("Couldn't DM {} about their claim #{}: {}", user_id, claim.id, err);
+    }
+}
+
+/// Handle a button in the review channel being pressed.
+async fn handle_button(
+    ctx: &serenity::Context,
+    conn: &DatabaseConnection,
+    interaction: &serenity::MessageComponentInteraction,
+    action: ReviewAction,
+    claim_id: i32,
+) -> Result<(), Error> {
+    if !interaction.member.as_ref().is_some_and(has_leadership_role) {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| {
+                    d.content("Only leadership can review claims.").ephemeral(true)
+                })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    match action {
+        ReviewAction::Approve => {
+            let result = approve_claim(conn, claim_id, interaction.user.id).await;
+            let claim = match result {
+                Ok(claim) => claim,
+                Err(err) => {
+                    interaction
+                        .create_interaction_response(ctx, |r| {
+                            r.interaction_response_data(|d| d.content(err).ephemeral(true))
+                        })
+                        .await?;
+                    return Ok(());
+                }
+            };
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.content(display_claim(&claim)).components(|c| c)
+                        })
+                })
+                .await?;
+            notify_officer(&ctx.http, &claim).await;
+        }
+        // Ask for the reason first, the claim is denied once the form is sent
+        ReviewAction::Deny => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(serenity::InteractionResponseType::Modal).interaction_response_data(
+                        |d| {
+                            d.custom_id(ReviewAction::DenyReason.custom_id(claim_id))
+                                .title(format!("Deny claim #{}", claim_id))
+                                .components(|c| {
+                                    c.create_action_row(|row| {
+                                        row.create_input_text(|t| {
+                                            t.custom_id("reason")
+                                                .label("Reason")
+                                                .style(serenity::InputTextStyle::Paragraph)
+                                                .required(true)
+                                        })
+                                    })
+                                })
+                        },
+                    )
+                })
+                .await?;
+        }
+        ReviewAction::DenyReason => {}
+    }
+    Ok(())
+}
+
+/// Handle the form with the reason for denying a claim being sent.
+async fn handle_deny_reason(
+    ctx: &serenity::Context,
+    conn: &DatabaseConnection,
+    interaction: &serenity::ModalSubmitInteraction,
+    claim_id: i32,
+) -> Result<(), Error> {
+    if !interaction.member.as_ref().is_some_and(has_leadership_role) {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| {
+                    d.content("Only leadership can review claims.").ephemeral(true)
+                })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let reason = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            serenity::ActionRowComponent::InputText(input) if input.custom_id == "reason" => {
+                Some(input.value.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let claim = match deny_claim(conn, claim_id, interaction.user.id, reason).await {
+        Ok(claim) => claim,
+        Err(err) => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.interaction_response_data(|d| d.content(err).ephemeral(true))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(serenity::InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.content(display_claim(&claim)).components(|c| c))
+        })
+        .await?;
+    notify_officer(&ctx.http, &claim).await;
+    Ok(())
+}
+
+pub async fn event_listener(
+    ctx: &serenity::Context,
+    event: &poise::Event<'_>,
+    _framework: &poise::Framework<Data, Error>,
+    user_data: &Data,
+) -> Result<(), Error> {
+    let conn = &user_data.db;
+    match event {
+        poise::Event::InteractionCreate {
+            interaction: serenity::Interaction::MessageComponent(interaction),
+        } => {
+            if let Some((action, claim_id)) = ReviewAction::parse(&interaction.data.custom_id) {
+                handle_button(ctx, conn, interaction, action, claim_id)
+                    .await
+                    .map_err(|err| format!("Failed handling a claim button: {}", err))?;
+            }
+        }
+        poise::Event::InteractionCreate {
+            interaction: serenity::Interaction::ModalSubmit(interaction),
+        } => {
+            if let Some((ReviewAction::DenyReason, claim_id)) =
+                ReviewAction::parse(&interaction.data.custom_id)
+            {
+                handle_deny_reason(ctx, conn, interaction, claim_id)
+                    .await
+                    .map_err(|err| format!("Failed denying a claim: {}", err))?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_action_custom_id() {
+        for action in [ReviewAction::Approve, ReviewAction::Deny, ReviewAction::DenyReason] {
+            assert_eq!(ReviewAction::parse(&action.custom_id(12)), Some((action, 12)));
+        }
+        assert_eq!(ReviewAction::parse("claim-approve-abc"), None);
+        assert_eq!(ReviewAction::parse("claim-other-12"), None);
+        // The page buttons of paginated messages
+        assert_eq!(ReviewAction::parse("1234-next"), None);
+    }
+
+    #[tokio::test]
+    async fn test_post_or_withdraw() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // A claim that fails to post is withdrawn
+        let withdrawn = AtomicBool::new(false);
+        let result: Result<(), Error> = post_or_withdraw(
+            3,
+            || async { Err("Missing access".into()) },
+            || async {
+                withdrawn.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        )
+        .await;
+        assert!(result.unwrap_err().to_string().contains("Missing access"));
+        assert!(withdrawn.load(Ordering::SeqCst));
+
+        // One that posted is kept
+        let withdrawn = AtomicBool::new(false);
+        let result = post_or_withdraw(
+            4,
+            || async { Ok(42) },
+            || async {
+                withdrawn.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert!(!withdrawn.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,962 @@
+use entity::event;
+use entity::event_host;
+use entity::officer;
+use entity::patrol;
+use entity::patrol_voice;
+use entity::saved_voice_channel;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::PaginatorTrait;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+use entity::sea_orm::TransactionTrait;
+use poise::serenity_prelude as serenity;
+
+use super::patrol_measure;
+use super::status_board::display_elapsed;
+use crate::config::CONFIG;
+use crate::global::{Error, PatrolCache};
+use std::collections::HashMap;
+
+/// Everything needed to create an event, or what an event is changed to when editing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventDetails {
+    pub name: String,
+    pub description: Option<String>,
+    pub start: chrono::NaiveDateTime,
+    pub end: chrono::NaiveDateTime,
+    /// The saved voice channel the event takes place in
+    pub channel_id: Option<i32>,
+    pub hosts: Vec<serenity::UserId>,
+}
+
+/// The changes to make to an event, everything that is [`None`] is left as it was.
+///
+/// The description and channel can be cleared by giving `Some(None)`.
+#[derive(Clone, Debug, Default)]
+pub struct EventChanges {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub start: Option<chrono::NaiveDateTime>,
+    pub end: Option<chrono::NaiveDateTime>,
+    pub channel_id: Option<Option<i32>>,
+    pub hosts: Option<Vec<serenity::UserId>>,
+}
+
+/// An event together with its hosts and the voice channel it takes place in.
+#[derive(Clone, Debug)]
+pub struct EventInfo {
+    pub event: event::Model,
+    pub hosts: Vec<serenity::UserId>,
+    pub channel: Option<saved_voice_channel::Model>,
+}
+
+impl EventDetails {
+    fn from_event(event: &event::Model, hosts: &[event_host::Model]) -> Self {
+        EventDetails {
+            name: event.name.clone(),
+            description: event.description.clone(),
+            start: event.start,
+            end: event.end,
+            channel_id: event.channel_id,
+            hosts: hosts.iter().map(|host| serenity::UserId(host.officer_id)).collect(),
+        }
+    }
+
+    fn apply(mut self, changes: EventChanges) -> Self {
+        self.name = changes.name.unwrap_or(self.name);
+        self.description = changes.description.unwrap_or(self.description);
+        self.start = changes.start.unwrap_or(self.start);
+        self.end = changes.end.unwrap_or(self.end);
+        self.channel_id = changes.channel_id.unwrap_or(self.channel_id);
+        self.hosts = changes.hosts.unwrap_or(self.hosts);
+        self
+    }
+}
+
+/// Check that the details of an event make sense, removing hosts that are given twice.
+pub fn validate_details(mut details: EventDetails) -> Result<EventDetails, Error> {
+    details.name = details.name.trim().to_owned();
+    if details.name.is_empty() {
+        return Err("An event needs a name.".into());
+    }
+    if details.end <= details.start {
+        return Err("An event has to end after it starts.".into());
+    }
+
+    let mut hosts = Vec::new();
+    for host in details.hosts {
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    if hosts.is_empty() {
+        return Err("An event needs at least one host.".into());
+    }
+    details.hosts = hosts;
+
+    Ok(details)
+}
+
+/// Make sure all the hosts are officers that are currently in the LPD.
+pub async fn check_hosts(
+    conn: &DatabaseConnection,
+    hosts: &[serenity::UserId],
+) -> Result<(), Error> {
+    let officer_count = officer::Entity::find()
+        .filter(officer::Column::Id.is_in(hosts.iter().map(|host| host.0)))
+        .filter(officer::Column::DeletedAt.is_null())
+        .count(conn)
+        .await?;
+    if officer_count != hosts.len() {
+        return Err("Only LPD officers can host events.".into());
+    }
+    Ok(())
+}
+
+/// Get an event and its hosts.
+async fn get_event_with_hosts(
+    conn: &DatabaseConnection,
+    event_id: i32,
+) -> Result<(event::Model, Vec<event_host::Model>), Error> {
+    Ok(event::Entity::find_by_id(event_id)
+        .find_with_related(event_host::Entity)
+        .all(conn)
+        .await?
+        .pop()
+        .ok_or(format!("There is no event with the id {}.", event_id))?)
+}
+
+/// Get an event, together with its hosts and voice channel.
+pub async fn get_event(conn: &DatabaseConnection, event_id: i32) -> Result<EventInfo, Error> {
+    let (event, hosts) = get_event_with_hosts(conn, event_id).await?;
+    Ok(add_channels(conn, vec![(event, hosts)]).await?.remove(0))
+}
+
+/// Look up the voice channels of events and combine everything into [`EventInfo`]s.
+async fn add_channels(
+    conn: &DatabaseConnection,
+    events: Vec<(event::Model, Vec<event_host::Model>)>,
+) -> Result<Vec<EventInfo>, Error> {
+    let channel_ids: Vec<_> = events.iter().filter_map(|(event, _)| event.channel_id).collect();
+    let channels = match channel_ids.is_empty() {
+        true => Vec::new(),
+        false => {
+            saved_voice_channel::Entity::find()
+                .filter(saved_voice_channel::Column::Id.is_in(channel_ids))
+                .all(conn)
+                .await?
+        }
+    };
+
+    Ok(events
+        .into_iter()
+        .map(|(event, hosts)| EventInfo {
+            channel: channels.iter().find(|channel| Some(channel.id) == event.channel_id).cloned(),
+            hosts: hosts.iter().map(|host| serenity::UserId(host.officer_id)).collect(),
+            event,
+        })
+        .collect())
+}
+
+/// Create a new event.
+pub async fn create_event(
+    conn: &DatabaseConnection,
+    details: EventDetails,
+) -> Result<event::Model, Error> {
+    let details = validate_details(details)?;
+    check_hosts(conn, &details.hosts).await?;
+    insert_event(conn, details, None).await
+}
+
+/// Create the event for a day of a recurring event.
+///
+/// Hosts of the recurring event that have left the LPD since it was set up are left out.
+pub async fn create_occurrence(
+    conn: &DatabaseConnection,
+    mut details: EventDetails,
+    template_id: i32,
+    date: chrono::NaiveDate,
+) -> Result<event::Model, Error> {
+    details.hosts = filter_officers(conn, details.hosts).await?;
+    insert_event(conn, details, Some((template_id, date))).await
+}
+
+/// Insert an event together with its hosts, the template and day are given for recurring events.
+async fn insert_event(
+    conn: &DatabaseConnection,
+    details: EventDetails,
+    occurrence: Option<(i32, chrono::NaiveDate)>,
+) -> Result<event::Model, Error> {
+    use entity::sea_orm::entity::*;
+    let active_model = event::ActiveModel {
+        start: Set(details.start),
+        end: Set(details.end),
+        name: Set(details.name),
+        description: Set(details.description),
+        channel_id: Set(details.channel_id),
+        cancelled_at: Set(None),
+        discord_event_id: Set(None),
+        template_id: Set(occurrence.map(|(template_id, _)| template_id)),
+        occurrence_date: Set(occurrence.map(|(_, date)| date)),
+        ..Default::default()
+    };
+
+    let txn = conn.begin().await?;
+    let event = active_model.insert(&txn).await?;
+    for host in details.hosts {
+        let host_model = event_host::ActiveModel {
+            event_id: Set(event.id),
+            officer_id: Set(host.0),
+            ..Default::default()
+        };
+        host_model.insert(&txn).await?;
+    }
+    txn.commit().await?;
+
+    Ok(event)
+}
+
+/// Change the details of an event that hasn't been cancelled.
+pub async fn edit_event(
+    conn: &DatabaseConnection,
+    event_id: i32,
+    changes: EventChanges,
+) -> Result<event::Model, Error> {
+    let (event, hosts) = get_event_with_hosts(conn, event_id).await?;
+    if event.cancelled_at.is_some() {
+        return Err(format!("The event {} has been cancelled.", event_id).into());
+    }
+    let hosts_changed = changes.hosts.is_some();
+    let details = validate_details(EventDetails::from_event(&event, &hosts).apply(changes))?;
+    if hosts_changed {
+        check_hosts(conn, &details.hosts).await?;
+    }
+
+    use entity::sea_orm::entity::*;
+    let txn = conn.begin().await?;
+    if hosts_changed {
+        event_host::Entity::delete_many()
+            .filter(event_host::Column::EventId.eq(event_id))
+            .exec(&txn)
+            .await?;
+        for host in &details.hosts {
+            let host_model = event_host::ActiveModel {
+                event_id: Set(event_id),
+                officer_id: Set(host.0),
+                ..Default::default()
+            };
+            host_model.insert(&txn).await?;
+        }
+    }
+
+    let mut active_model: event::ActiveModel = event.into();
+    active_model.name = Set(details.name);
+    active_model.description = Set(details.description);
+    active_model.start = Set(details.start);
+    active_model.end = Set(details.end);
+    active_model.channel_id = Set(details.channel_id);
+    let event = active_model.update(&txn).await?;
+    txn.commit().await?;
+
+    Ok(event)
+}
+
+/// Cancel an event that hasn't ended yet, it is kept in the database so it can still be listed.
+pub async fn cancel_event(
+    conn: &DatabaseConnection,
+    event_id: i32,
+    now: chrono::NaiveDateTime,
+) -> Result<event::Model, Error> {
+    let event = event::Entity::find_by_id(event_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no event with the id {}.", event_id))?;
+    if event.cancelled_at.is_some() {
+        return Err(format!("The event {} has already been cancelled.", event_id).into());
+    }
+    if event.end <= now {
+        return Err(format!("The event {} has already ended.", event_id).into());
+    }
+
+    use entity::sea_orm::entity::*;
+    let mut active_model: event::ActiveModel = event.into();
+    active_model.cancelled_at = Set(Some(now));
+    Ok(active_model.update(conn).await?)
+}
+
+/// Create or update the event that is synced with a Discord scheduled event.
+///
+/// Running this more than once for the same scheduled event gives the same result. The hosts are
+/// added to the ones the event already has, so hosts added with the bot aren't lost, and an event
+/// can end up without any hosts if the one that created it on Discord isn't an officer.
+pub async fn save_discord_event(
+    conn: &DatabaseConnection,
+    discord_event_id: u64,
+    details: EventDetails,
+    cancelled_at: Option<chrono::NaiveDateTime>,
+) -> Result<event::Model, Error> {
+    let existing = event::Entity::find()
+        .find_with_related(event_host::Entity)
+        .filter(event::Column::DiscordEventId.eq(discord_event_id))
+        .all(conn)
+        .await?
+        .pop();
+
+    use entity::sea_orm::entity::*;
+    let txn = conn.begin().await?;
+    let (event, hosts) = match existing {
+        Some((event, hosts)) => {
+            // Keep the time the event was cancelled at if it was cancelled before
+            let cancelled_at = cancelled_at.map(|now| event.cancelled_at.unwrap_or(now));
+            let mut active_model: event::ActiveModel = event.into();
+            active_model.name = Set(details.name);
+            active_model.description = Set(details.description);
+            active_model.start = Set(details.start);
+            active_model.end = Set(details.end);
+            active_model.channel_id = Set(details.channel_id);
+            active_model.cancelled_at = Set(cancelled_at);
+            (active_model.update(&txn).await?, hosts)
+        }
+        None => {
+            let active_model = event::ActiveModel {
+                start: Set(details.start),
+                end: Set(details.end),
+                name: Set(details.name),
+                description: Set(details.description),
+                channel_id: Set(details.channel_id),
+                cancelled_at: Set(cancelled_at),
+                discord_event_id: Set(Some(discord_event_id)),
+                template_id: Set(None),
+                occurrence_date: Set(None),
+                ..Default::default()
+            };
+            (active_model.insert(&txn).await?, Vec::new())
+        }
+    };
+
+    for host in details.hosts {
+        if hosts.iter().any(|existing_host| existing_host.officer_id == host.0) {
+            continue;
+        }
+        let host_model = event_host::ActiveModel {
+            event_id: Set(event.id),
+            officer_id: Set(host.0),
+            ..Default::default()
+        };
+        host_model.insert(&txn).await?;
+    }
+    txn.commit().await?;
+
+    Ok(event)
+}
+
+/// Cancel the event that is synced with a Discord scheduled event that was deleted, if there is one.
+pub async fn cancel_discord_event(
+    conn: &DatabaseConnection,
+    discord_event_id: u64,
+    now: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    use entity::sea_orm::sea_query::Expr;
+    event::Entity::update_many()
+        .col_expr(event::Column::CancelledAt, Expr::value(now))
+        .filter(event::Column::DiscordEventId.eq(discord_event_id))
+        .filter(event::Column::CancelledAt.is_null())
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Get the officers out of the users given, leaving out everyone that isn't in the LPD.
+pub async fn filter_officers(
+    conn: &DatabaseConnection,
+    users: Vec<serenity::UserId>,
+) -> Result<Vec<serenity::UserId>, Error> {
+    if users.is_empty() {
+        return Ok(users);
+    }
+    let officers = officer::Entity::find()
+        .filter(officer::Column::Id.is_in(users.iter().map(|user| user.0)))
+        .filter(officer::Column::DeletedAt.is_null())
+        .all(conn)
+        .await?;
+    Ok(users.into_iter().filter(|user| officers.iter().any(|o| o.id == user.0)).collect())
+}
+
+/// Get the events that haven't ended yet and weren't cancelled, the first one to start first.
+pub async fn get_upcoming_events(
+    conn: &DatabaseConnection,
+    now: chrono::NaiveDateTime,
+) -> Result<Vec<EventInfo>, Error> {
+    let events = event::Entity::find()
+        .find_with_related(event_host::Entity)
+        .filter(event::Column::End.gt(now))
+        .filter(event::Column::CancelledAt.is_null())
+        .order_by_asc(event::Column::Start)
+        .order_by_asc(event::Column::Id)
+        .all(conn)
+        .await?;
+    add_channels(conn, events).await
+}
+
+/// Get the events with the ids given.
+pub async fn get_events(
+    conn: &DatabaseConnection,
+    event_ids: Vec<i32>,
+) -> Result<HashMap<i32, event::Model>, Error> {
+    if event_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let events = event::Entity::find().filter(event::Column::Id.is_in(event_ids)).all(conn).await?;
+    Ok(events.into_iter().map(|event| (event.id, event)).collect())
+}
+
+/// The time an officer spent in a voice channel, the channel is a saved voice channel id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelSpan {
+    pub channel_id: i32,
+    pub start: chrono::NaiveDateTime,
+    pub end: chrono::NaiveDateTime,
+}
+
+/// Get how many seconds of a time span fall inside the start and end given.
+fn overlap_seconds(
+    span_start: chrono::NaiveDateTime,
+    span_end: chrono::NaiveDateTime,
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+) -> i64 {
+    std::cmp::min(span_end, end)
+        .signed_duration_since(std::cmp::max(span_start, start))
+        .num_seconds()
+        .max(0)
+}
+
+/// Pick the event a patrol spent the most time in, out of the events given.
+fn pick_event(events: Vec<event::Model>, spans: &[ChannelSpan]) -> Option<event::Model> {
+    let time_in_event = |event: &event::Model| -> i64 {
+        spans
+            .iter()
+            .filter(|span| Some(span.channel_id) == event.channel_id)
+            .map(|span| overlap_seconds(span.start, span.end, event.start, event.end))
+            .sum()
+    };
+    events
+        .into_iter()
+        .map(|event| (time_in_event(&event), event))
+        .filter(|(seconds, _)| *seconds > 0)
+        .max_by_key(|(seconds, event)| (*seconds, std::cmp::Reverse(event.start)))
+        .map(|(_, event)| event)
+}
+
+/// Find the event a patrol was part of, which is the event it spent the most time in the voice
+/// channel of while the event was going on.
+pub async fn find_patrol_event(
+    conn: &DatabaseConnection,
+    spans: &[ChannelSpan],
+) -> Result<Option<event::Model>, Error> {
+    let start = match spans.iter().map(|span| span.start).min() {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let end = spans.iter().map(|span| span.end).max().unwrap_or(start);
+    let events = event::Entity::find()
+        .filter(event::Column::ChannelId.is_in(spans.iter().map(|span| span.channel_id)))
+        .filter(event::Column::CancelledAt.is_null())
+        .filter(event::Column::Start.lt(end))
+        .filter(event::Column::End.gt(start))
+        .all(conn)
+        .await?;
+    Ok(pick_event(events, spans))
+}
+
+/// When an officer was in the voice channel of an event while it was going on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attendance {
+    pub officer_id: serenity::UserId,
+    /// The first time the officer joined during the event
+    pub joined: chrono::NaiveDateTime,
+    /// The last time the officer left during the event, or the end of the event if they stayed
+    pub left: chrono::NaiveDateTime,
+    /// How long the officer was there in total
+    pub seconds: i64,
+}
+
+/// Work out the attendance of an event from the times officers were in its voice channel.
+///
+/// The times are clipped to the event, times of the same officer that overlap are only counted
+/// once. The officers that were there first are listed first.
+fn combine_attendance(
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    mut presences: Vec<(serenity::UserId, chrono::NaiveDateTime, chrono::NaiveDateTime)>,
+) -> Vec<Attendance> {
+    presences.sort_by_key(|(officer_id, span_start, _)| (officer_id.0, *span_start));
+
+    let mut attendance: Vec<Attendance> = Vec::new();
+    for (officer_id, span_start, span_end) in presences {
+        let span_start = std::cmp::max(span_start, start);
+        let span_end = std::cmp::min(span_end, end);
+        if span_end <= span_start {
+            continue;
+        }
+
+        match attendance.last_mut() {
+            // The times are sorted by when they start, so only the part after the time counted so
+            // far is new
+            Some(last) if last.officer_id == officer_id => {
+                last.seconds += overlap_seconds(span_start, span_end, last.left, end);
+                last.left = std::cmp::max(last.left, span_end);
+            }
+            _ => attendance.push(Attendance {
+                officer_id,
+                joined: span_start,
+                left: span_end,
+                seconds: span_end.signed_duration_since(span_start).num_seconds(),
+            }),
+        }
+    }
+
+    attendance.sort_by_key(|item| (item.joined, item.officer_id.0));
+    attendance
+}
+
+/// Get the attendance of an event from the stored patrols and the patrols going on at the moment.
+pub async fn get_attendance(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    event_id: i32,
+    now: chrono::NaiveDateTime,
+) -> Result<(EventInfo, Vec<Attendance>), Error> {
+    let info = get_event(conn, event_id).await?;
+    let channel = info
+        .channel
+        .clone()
+        .ok_or(format!("The event {} doesn't have a voice channel.", event_id))?;
+    let (start, end) = (info.event.start, info.event.end);
+
+    // The time spent in the channel by patrols that have ended
+    let stored = patrol_voice::Entity::find()
+        .find_also_related(patrol::Entity)
+        .filter(patrol_voice::Column::ChannelId.eq(channel.id))
+        .filter(patrol_voice::Column::Start.lt(end))
+        .filter(patrol_voice::Column::End.gt(start))
+        .filter(patrol::Column::Ignored.eq(false))
+        .all(conn)
+        .await?;
+    let mut presences: Vec<_> = stored
+        .into_iter()
+        .filter_map(|(pat_vc, patrol)| {
+            Some((serenity::UserId(patrol?.officer_id), pat_vc.start, pat_vc.end))
+        })
+        .collect();
+
+    // The time spent in the channel by patrols that are still going on
+    let patrol_cache_lock = patrol_cache.read().await;
+    for patrol_log in patrol_cache_lock.values() {
+        presences.extend(
+            patrol_log
+                .voice_log
+                .iter()
+                .filter(|channel_log| channel_log.channel_id.0 == channel.channel_id)
+                .map(|channel_log| {
+                    (patrol_log.officer_id, channel_log.start, channel_log.end.unwrap_or(now))
+                }),
+        );
+    }
+    drop(patrol_cache_lock);
+
+    Ok((info, combine_attendance(start, end, presences)))
+}
+
+/// Count the events that were attended by a patrol with the voice channel times given, which are
+/// the events it was in the voice channel of while they were going on.
+fn count_attended(events: &[event::Model], spans: &[ChannelSpan]) -> usize {
+    events
+        .iter()
+        .filter(|event| {
+            spans.iter().any(|span| {
+                Some(span.channel_id) == event.channel_id
+                    && overlap_seconds(span.start, span.end, event.start, event.end) > 0
+            })
+        })
+        .count()
+}
+
+/// Count the events an officer attended between the from and to times, which are the events they
+/// were in the voice channel of while the event was going on.
+pub async fn count_events_attended(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    user_id: serenity::UserId,
+) -> Result<usize, Error> {
+    let stored = patrol_voice::Entity::find()
+        .find_also_related(patrol::Entity)
+        .filter(patrol::Column::OfficerId.eq(user_id.0))
+        .filter(patrol::Column::Ignored.eq(false))
+        .filter(patrol_voice::Column::Start.lte(to))
+        .filter(patrol_voice::Column::End.gte(from))
+        .all(conn)
+        .await?;
+    let spans: Vec<_> = stored
+        .into_iter()
+        .filter(|(_, patrol)| patrol.is_some())
+        .map(|(pat_vc, _)| ChannelSpan {
+            channel_id: pat_vc.channel_id,
+            start: pat_vc.start,
+            end: pat_vc.end,
+        })
+        .collect();
+    if spans.is_empty() {
+        return Ok(0);
+    }
+
+    let events = event::Entity::find()
+        .filter(event::Column::ChannelId.is_in(spans.iter().map(|span| span.channel_id)))
+        .filter(event::Column::CancelledAt.is_null())
+        .filter(event::Column::Start.lte(to))
+        .filter(event::Column::End.gte(from))
+        .all(conn)
+        .await?;
+    Ok(count_attended(&events, &spans))
+}
+
+/// How many events an officer hosted and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostStats {
+    pub officer_id: serenity::UserId,
+    pub events: usize,
+    pub seconds: i64,
+}
+
+/// Add up the events each officer hosted, the officers that hosted for the longest first.
+fn sum_host_stats(hosted: &[(u64, event::Model)]) -> Vec<HostStats> {
+    let mut stats: Vec<HostStats> = Vec::new();
+    for (officer_id, event) in hosted {
+        let seconds = event.end.signed_duration_since(event.start).num_seconds();
+        match stats.iter_mut().find(|item| item.officer_id.0 == *officer_id) {
+            Some(item) => {
+                item.events += 1;
+                item.seconds += seconds;
+            }
+            None => stats.push(HostStats {
+                officer_id: serenity::UserId(*officer_id),
+                events: 1,
+                seconds,
+            }),
+        }
+    }
+    stats.sort_by_key(|item| (std::cmp::Reverse(item.seconds), item.officer_id.0));
+    stats
+}
+
+/// Get how many events each officer hosted that started between the from and to times.
+///
+/// Cancelled events are left out, the hosted time is the whole length of each event.
+pub async fn get_host_stats(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<Vec<HostStats>, Error> {
+    let hosted = event_host::Entity::find()
+        .find_also_related(event::Entity)
+        .filter(event::Column::Start.gte(from))
+        .filter(event::Column::Start.lte(to))
+        .filter(event::Column::CancelledAt.is_null())
+        .all(conn)
+        .await?;
+    let hosted: Vec<_> =
+        hosted.into_iter().filter_map(|(host, event)| Some((host.officer_id, event?))).collect();
+    Ok(sum_host_stats(&hosted))
+}
+
+/// Get how much of a time span isn't covered by the times given, which can't overlap each other.
+fn uncovered_seconds(
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    covered: &[(chrono::NaiveDateTime, chrono::NaiveDateTime)],
+) -> i64 {
+    let covered_seconds: i64 =
+        covered.iter().map(|(c_start, c_end)| overlap_seconds(*c_start, *c_end, start, end)).sum();
+    end.signed_duration_since(start).num_seconds() - covered_seconds
+}
+
+/// Get the time an officer spent hosting events between the from and to times while they weren't
+/// on patrol, so it isn't counted twice when added to their patrol time.
+pub async fn get_host_time(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    user_id: serenity::UserId,
+) -> Result<i64, Error> {
+    let hosted = event_host::Entity::find()
+        .find_also_related(event::Entity)
+        .filter(event_host::Column::OfficerId.eq(user_id.0))
+        .filter(event::Column::Start.lt(to))
+        .filter(event::Column::End.gt(from))
+        .filter(event::Column::CancelledAt.is_null())
+        .all(conn)
+        .await?;
+    if hosted.is_empty() {
+        return Ok(0);
+    }
+
+    let patrols = patrol_measure::get_patrols(conn, from, to, user_id).await?;
+    let patrol_times: Vec<_> =
+        patrols.iter().map(|(patrol, _)| (patrol.start, patrol.end)).collect();
+    Ok(hosted
+        .into_iter()
+        .filter_map(|(_, event)| event)
+        .map(|event| {
+            let start = std::cmp::max(event.start, from);
+            let end = std::cmp::min(event.end, to);
+            uncovered_seconds(start, end, &patrol_times)
+        })
+        .sum())
+}
+
+/// Get the time of an officer between the from and to times that counts towards the activity
+/// requirements, which includes the time they hosted events if that is turned on in the settings.
+pub async fn get_counted_time(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    user_id: serenity::UserId,
+) -> Result<i64, Error> {
+    let patrol_time = patrol_measure::get_patrol_time(conn, from, to, user_id).await?;
+    let host_time = match CONFIG.events.count_host_time {
+        true => get_host_time(conn, from, to, user_id).await?,
+        false => 0,
+    };
+    Ok(patrol_time.counted() + host_time)
+}
+
+/// Make a single line string that represents the attendance of an officer.
+pub fn display_attendance(attendance: &Attendance, name: &str) -> String {
+    format!(
+        "{} - joined {} - left {} - {}",
+        name,
+        attendance.joined.format("%H:%M"),
+        attendance.left.format("%H:%M"),
+        display_elapsed(attendance.seconds)
+    )
+}
+
+/// Make a string that represents an event, the description goes on a line of its own.
+pub fn display_event(info: &EventInfo) -> String {
+    let event = &info.event;
+    let mut result = format!(
+        "#{} {} - {} to {}",
+        event.id,
+        event.name,
+        event.start.format("%Y-%m-%d %H:%M"),
+        event.end.format("%Y-%m-%d %H:%M")
+    );
+    if let Some(channel) = &info.channel {
+        result += &format!(" in <#{}>", channel.channel_id);
+    }
+    if !info.hosts.is_empty() {
+        let hosts: Vec<_> = info.hosts.iter().map(|host| format!("<@{}>", host.0)).collect();
+        result += &format!(", hosted by {}", hosts.join(", "));
+    }
+    if event.cancelled_at.is_some() {
+        result += " (cancelled)";
+    }
+    if let Some(description) = &event.description {
+        result += &format!("\n    {}", description);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_time(hour: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2022, 5, 24).and_hms(hour, 0, 0)
+    }
+
+    fn details() -> EventDetails {
+        EventDetails {
+            name: " Training ".to_owned(),
+            description: None,
+            start: date_time(19),
+            end: date_time(21),
+            channel_id: None,
+            hosts: vec![serenity::UserId(1), serenity::UserId(2), serenity::UserId(1)],
+        }
+    }
+
+    #[test]
+    fn test_validate_details() {
+        let validated = validate_details(details()).unwrap();
+        assert_eq!(validated.name, "Training");
+        assert_eq!(validated.hosts, vec![serenity::UserId(1), serenity::UserId(2)]);
+
+        assert!(validate_details(EventDetails { end: date_time(19), ..details() }).is_err());
+        assert!(validate_details(EventDetails { name: " ".to_owned(), ..details() }).is_err());
+        assert!(validate_details(EventDetails { hosts: Vec::new(), ..details() }).is_err());
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let event = EventDetails {
+            description: Some("Practice arrests".to_owned()),
+            channel_id: Some(3),
+            ..details()
+        };
+
+        let unchanged = event.clone().apply(EventChanges::default());
+        assert_eq!(unchanged, event);
+
+        let changes = EventChanges {
+            name: Some("Patrol".to_owned()),
+            description: Some(None),
+            ..Default::default()
+        };
+        let changed = event.clone().apply(changes);
+        assert_eq!(changed.name, "Patrol");
+        assert_eq!(changed.description, None);
+        assert_eq!(changed.channel_id, Some(3));
+
+        let changes = EventChanges { channel_id: Some(None), ..Default::default() };
+        let changed = event.clone().apply(changes);
+        assert_eq!(changed.description, event.description);
+        assert_eq!(changed.channel_id, None);
+    }
+
+    #[test]
+    fn test_display_event() {
+        let mut info = EventInfo {
+            event: event::Model {
+                id: 3,
+                start: date_time(19),
+                end: date_time(21),
+                name: "Training".to_owned(),
+                description: Some("Bring a car".to_owned()),
+                channel_id: None,
+                cancelled_at: None,
+                discord_event_id: None,
+                template_id: None,
+                occurrence_date: None,
+            },
+            hosts: vec![serenity::UserId(1), serenity::UserId(2)],
+            channel: None,
+        };
+        assert_eq!(
+            display_event(&info),
+            "#3 Training - 2022-05-24 19:00 to 2022-05-24 21:00, hosted by <@1>, <@2>\n    Bring a car"
+        );
+
+        info.event.description = None;
+        info.event.cancelled_at = Some(date_time(12));
+        info.hosts = Vec::new();
+        assert_eq!(
+            display_event(&info),
+            "#3 Training - 2022-05-24 19:00 to 2022-05-24 21:00 (cancelled)"
+        );
+    }
+
+    fn span(channel_id: i32, start: u32, end: u32) -> ChannelSpan {
+        ChannelSpan { channel_id, start: date_time(start), end: date_time(end) }
+    }
+
+    fn event_in(id: i32, channel_id: i32, start: u32, end: u32) -> event::Model {
+        event::Model {
+            id,
+            start: date_time(start),
+            end: date_time(end),
+            name: "Event".to_owned(),
+            description: None,
+            channel_id: Some(channel_id),
+            cancelled_at: None,
+            discord_event_id: None,
+            template_id: None,
+            occurrence_date: None,
+        }
+    }
+
+    #[test]
+    fn test_pick_event() {
+        let events = vec![event_in(1, 1, 12, 14), event_in(2, 2, 13, 17), event_in(3, 1, 16, 18)];
+        let pick = |spans: &[ChannelSpan]| pick_event(events.clone(), spans).map(|e| e.id);
+
+        // Most of the patrol was in the second event
+        assert_eq!(pick(&[span(1, 11, 13), span(2, 13, 16)]), Some(2));
+        // The first event is in another channel
+        assert_eq!(pick(&[span(2, 12, 13)]), None);
+        assert_eq!(pick(&[span(1, 14, 16)]), None);
+        assert_eq!(pick(&[]), None);
+    }
+
+    #[test]
+    fn test_count_attended() {
+        let events = vec![event_in(1, 1, 12, 14), event_in(2, 2, 15, 17), event_in(3, 1, 18, 20)];
+
+        // One patrol that went from the first event to the second one
+        assert_eq!(count_attended(&events, &[span(1, 13, 15), span(2, 15, 16)]), 2);
+        // Being in the channel more than once during an event counts it once
+        assert_eq!(count_attended(&events, &[span(1, 12, 13), span(1, 13, 14)]), 1);
+        // In the channel of an event, but not while it was going on
+        assert_eq!(count_attended(&events, &[span(1, 14, 18), span(2, 12, 15)]), 0);
+        assert_eq!(count_attended(&events, &[]), 0);
+    }
+
+    #[test]
+    fn test_combine_attendance() {
+        let (one, two) = (serenity::UserId(1), serenity::UserId(2));
+        let presences = vec![
+            (two, date_time(18), date_time(23)),
+            // Times that overlap are only counted once
+            (one, date_time(20), date_time(21)),
+            (one, date_time(19), date_time(20) + chrono::Duration::minutes(30)),
+            (one, date_time(20), date_time(22)),
+            // Before the event
+            (two, date_time(12), date_time(13)),
+        ];
+        assert_eq!(
+            combine_attendance(date_time(19), date_time(22), presences),
+            vec![
+                Attendance {
+                    officer_id: one,
+                    joined: date_time(19),
+                    left: date_time(22),
+                    seconds: 3 * 3600
+                },
+                Attendance {
+                    officer_id: two,
+                    joined: date_time(19),
+                    left: date_time(22),
+                    seconds: 3 * 3600
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sum_host_stats() {
+        let hosted = vec![
+            (1, event_in(1, 1, 12, 14)),
+            (2, event_in(1, 1, 12, 14)),
+            (2, event_in(2, 1, 16, 17)),
+            (3, event_in(3, 1, 18, 22)),
+        ];
+        let stats = |officer_id, events, hours: i64| HostStats {
+            officer_id: serenity::UserId(officer_id),
+            events,
+            seconds: hours * 3600,
+        };
+        assert_eq!(sum_host_stats(&hosted), vec![stats(3, 1, 4), stats(2, 2, 3), stats(1, 1, 2)]);
+        assert_eq!(sum_host_stats(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_uncovered_seconds() {
+        let patrols = vec![(date_time(10), date_time(13)), (date_time(15), date_time(16))];
+        assert_eq!(uncovered_seconds(date_time(12), date_time(18), &patrols), 4 * 3600);
+        assert_eq!(uncovered_seconds(date_time(11), date_time(12), &patrols), 0);
+        assert_eq!(uncovered_seconds(date_time(18), date_time(19), &patrols), 3600);
+    }
+}
@@ -0,0 +1,411 @@
+use chrono::Datelike;
+use entity::event;
+use entity::event_template;
+use entity::event_template_host;
+use entity::sea_orm::sea_query::Expr;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+use entity::sea_orm::TransactionTrait;
+use poise::serenity_prelude as serenity;
+
+use super::event::{self as event_module, EventDetails};
+use crate::config::CONFIG;
+use crate::global::Error;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// When a recurring event takes place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    /// Every week on the days given
+    Weekly(Vec<chrono::Weekday>),
+    /// Every number of days given, counting from the first day of the recurring event
+    EveryDays(i64),
+    /// Every month on the nth weekday, where a week of -1 is the last one in the month
+    Monthly { week: i64, weekday: chrono::Weekday },
+}
+
+fn display_weekday(weekday: chrono::Weekday) -> String {
+    weekday.to_string().to_lowercase()
+}
+
+fn parse_weekday(text: &str) -> Result<chrono::Weekday, Error> {
+    chrono::Weekday::from_str(text)
+        .map_err(|_| format!("\"{}\" isn't a day of the week.", text).into())
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Weekly(weekdays) => {
+                let weekdays: Vec<_> = weekdays.iter().map(|day| display_weekday(*day)).collect();
+                write!(f, "weekly {}", weekdays.join(","))
+            }
+            Recurrence::EveryDays(days) => write!(f, "every {} days", days),
+            Recurrence::Monthly { week: -1, weekday } => {
+                write!(f, "monthly last {}", display_weekday(*weekday))
+            }
+            Recurrence::Monthly { week, weekday } => {
+                write!(f, "monthly {} {}", week, display_weekday(*weekday))
+            }
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = Error;
+
+    /// Read a recurrence in the same format it is displayed in, like "weekly mon,thu",
+    /// "every 3 days", "monthly 2 tue" or "monthly last fri".
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().to_lowercase();
+        let words: Vec<_> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["weekly", weekdays @ ..] => {
+                let mut days = Vec::new();
+                for day in weekdays.join(",").split(',').filter(|day| !day.is_empty()) {
+                    let day = parse_weekday(day)?;
+                    if !days.contains(&day) {
+                        days.push(day);
+                    }
+                }
+                if days.is_empty() {
+                    return Err("A weekly recurrence needs at least one day of the week.".into());
+                }
+                days.sort_by_key(|day| day.num_days_from_monday());
+                Ok(Recurrence::Weekly(days))
+            }
+            ["every", days, "days" | "day"] => match days.parse() {
+                Ok(days) if days > 0 => Ok(Recurrence::EveryDays(days)),
+                _ => Err("The number of days has to be a whole number above 0.".into()),
+            },
+            ["monthly", week, weekday] => {
+                let week = match *week {
+                    "last" => -1,
+                    week => match week.trim_end_matches(|c: char| c.is_alphabetic()).parse() {
+                        Ok(week) if (1..=5).contains(&week) => week,
+                        _ => return Err("The week of the month has to be 1 to 5 or last.".into()),
+                    },
+                };
+                Ok(Recurrence::Monthly { week, weekday: parse_weekday(weekday)? })
+            }
+            _ => Err(format!(
+                "\"{}\" isn't a valid recurrence, use something like \"weekly mon,thu\", \"every 3 days\", \"monthly 2 tue\" or \"monthly last fri\".",
+                text
+            )
+            .into()),
+        }
+    }
+}
+
+impl Recurrence {
+    /// Check if a recurring event that started on the first day given takes place on a day.
+    pub fn occurs_on(&self, first_date: chrono::NaiveDate, date: chrono::NaiveDate) -> bool {
+        if date < first_date {
+            return false;
+        }
+        match self {
+            Recurrence::Weekly(weekdays) => weekdays.contains(&date.weekday()),
+            Recurrence::EveryDays(days) => {
+                date.signed_duration_since(first_date).num_days() % days == 0
+            }
+            Recurrence::Monthly { week, weekday } => {
+                let week_of_month = match *week {
+                    -1 => match (date + chrono::Duration::days(7)).month() == date.month() {
+                        true => 0,
+                        false => -1,
+                    },
+                    _ => (date.day() as i64 - 1) / 7 + 1,
+                };
+                date.weekday() == *weekday && week_of_month == *week
+            }
+        }
+    }
+
+    /// Get the days between the from and to dates, which are both inclusive, the recurring event
+    /// takes place on.
+    pub fn dates(
+        &self,
+        first_date: chrono::NaiveDate,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+    ) -> Vec<chrono::NaiveDate> {
+        let mut dates = Vec::new();
+        let mut date = std::cmp::max(from, first_date);
+        while date <= to {
+            if self.occurs_on(first_date, date) {
+                dates.push(date);
+            }
+            date = date.succ();
+        }
+        dates
+    }
+}
+
+/// Everything needed to set up a recurring event.
+#[derive(Clone, Debug)]
+pub struct TemplateDetails {
+    pub name: String,
+    pub description: Option<String>,
+    pub recurrence: Recurrence,
+    pub first_date: chrono::NaiveDate,
+    pub start_time: chrono::NaiveTime,
+    pub duration_minutes: i32,
+    /// The saved voice channel the events take place in
+    pub channel_id: Option<i32>,
+    pub hosts: Vec<serenity::UserId>,
+}
+
+/// Get the details of the event a template creates on a day.
+fn occurrence_details(
+    template: &event_template::Model,
+    hosts: &[event_template_host::Model],
+    date: chrono::NaiveDate,
+) -> EventDetails {
+    let start = date.and_time(template.start_time);
+    EventDetails {
+        name: template.name.clone(),
+        description: template.description.clone(),
+        start,
+        end: start + chrono::Duration::minutes(template.duration_minutes as i64),
+        channel_id: template.channel_id,
+        hosts: hosts.iter().map(|host| serenity::UserId(host.officer_id)).collect(),
+    }
+}
+
+/// Set up a recurring event, the events for the coming days are created right away.
+pub async fn create_template(
+    conn: &DatabaseConnection,
+    details: TemplateDetails,
+    today: chrono::NaiveDate,
+) -> Result<event_template::Model, Error> {
+    if details.duration_minutes <= 0 {
+        return Err("A recurring event has to last longer than 0 minutes.".into());
+    }
+    let start = details.first_date.and_time(details.start_time);
+    let checked = event_module::validate_details(EventDetails {
+        name: details.name,
+        description: details.description,
+        start,
+        end: start + chrono::Duration::minutes(details.duration_minutes as i64),
+        channel_id: details.channel_id,
+        hosts: details.hosts,
+    })?;
+    event_module::check_hosts(conn, &checked.hosts).await?;
+
+    use entity::sea_orm::entity::*;
+    let active_model = event_template::ActiveModel {
+        name: Set(checked.name),
+        description: Set(checked.description),
+        recurrence: Set(details.recurrence.to_string()),
+        first_date: Set(details.first_date),
+        start_time: Set(details.start_time),
+        duration_minutes: Set(details.duration_minutes),
+        channel_id: Set(checked.channel_id),
+        created_until: Set(None),
+        ended_at: Set(None),
+        ..Default::default()
+    };
+
+    let txn = conn.begin().await?;
+    let template = active_model.insert(&txn).await?;
+    let mut hosts = Vec::new();
+    for host in checked.hosts {
+        let host_model = event_template_host::ActiveModel {
+            template_id: Set(template.id),
+            officer_id: Set(host.0),
+            ..Default::default()
+        };
+        hosts.push(host_model.insert(&txn).await?);
+    }
+    txn.commit().await?;
+
+    create_template_occurrences(conn, template, &hosts, today).await
+}
+
+/// Create the events of a template up to the number of days ahead in the settings.
+///
+/// Days that already have an event are skipped, so events that were edited or cancelled on their
+/// own stay as they are.
+async fn create_template_occurrences(
+    conn: &DatabaseConnection,
+    template: event_template::Model,
+    hosts: &[event_template_host::Model],
+    today: chrono::NaiveDate,
+) -> Result<event_template::Model, Error> {
+    let recurrence: Recurrence = template.recurrence.parse()?;
+    let from = match template.created_until {
+        Some(created_until) => created_until.succ(),
+        None => today,
+    };
+    let to = today + chrono::Duration::days(CONFIG.events.create_days_ahead);
+    if from > to {
+        return Ok(template);
+    }
+
+    let existing: Vec<_> = event::Entity::find()
+        .filter(event::Column::TemplateId.eq(template.id))
+        .filter(event::Column::OccurrenceDate.gte(from))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter_map(|event| event.occurrence_date)
+        .collect();
+    for date in recurrence.dates(template.first_date, from, to) {
+        if !existing.contains(&date) {
+            let details = occurrence_details(&template, hosts, date);
+            event_module::create_occurrence(conn, details, template.id, date).await?;
+        }
+    }
+
+    use entity::sea_orm::entity::*;
+    let mut active_model: event_template::ActiveModel = template.into();
+    active_model.created_until = Set(Some(to));
+    Ok(active_model.update(conn).await?)
+}
+
+/// Create the upcoming events of all the recurring events that haven't ended.
+///
+/// Returns how many recurring events were brought up to date and how many failed.
+pub async fn create_occurrences(
+    conn: &DatabaseConnection,
+    today: chrono::NaiveDate,
+) -> Result<(usize, usize), Error> {
+    let templates = event_template::Entity::find()
+        .find_with_related(event_template_host::Entity)
+        .filter(event_template::Column::EndedAt.is_null())
+        .all(conn)
+        .await?;
+
+    let (mut updated, mut failed) = (0, 0);
+    for (template, hosts) in templates {
+        let template_id = template.id;
+        match create_template_occurrences(conn, template, &hosts, today).await {
+            Ok(_) => updated += 1,
+            Err(err) => {
+                println!("Failed creating the events of recurring event {}: {}", template_id, err);
+                failed += 1;
+            }
+        }
+    }
+    Ok((updated, failed))
+}
+
+/// Stop a recurring event, the events it created that haven't started yet are cancelled.
+pub async fn end_template(
+    conn: &DatabaseConnection,
+    template_id: i32,
+    now: chrono::NaiveDateTime,
+) -> Result<event_template::Model, Error> {
+    let template = event_template::Entity::find_by_id(template_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no recurring event with the id {}.", template_id))?;
+    if template.ended_at.is_some() {
+        return Err(format!("The recurring event {} has already ended.", template_id).into());
+    }
+
+    use entity::sea_orm::entity::*;
+    let txn = conn.begin().await?;
+    event::Entity::update_many()
+        .col_expr(event::Column::CancelledAt, Expr::value(now))
+        .filter(event::Column::TemplateId.eq(template_id))
+        .filter(event::Column::Start.gt(now))
+        .filter(event::Column::CancelledAt.is_null())
+        .exec(&txn)
+        .await?;
+    let mut active_model: event_template::ActiveModel = template.into();
+    active_model.ended_at = Set(Some(now));
+    let template = active_model.update(&txn).await?;
+    txn.commit().await?;
+
+    Ok(template)
+}
+
+/// Get the recurring events that haven't ended, together with their hosts.
+pub async fn get_templates(
+    conn: &DatabaseConnection,
+) -> Result<Vec<(event_template::Model, Vec<event_template_host::Model>)>, Error> {
+    Ok(event_template::Entity::find()
+        .find_with_related(event_template_host::Entity)
+        .filter(event_template::Column::EndedAt.is_null())
+        .order_by_asc(event_template::Column::Id)
+        .all(conn)
+        .await?)
+}
+
+/// Make a single line string that represents a recurring event.
+pub fn display_template(
+    template: &event_template::Model,
+    hosts: &[event_template_host::Model],
+) -> String {
+    let mut result = format!(
+        "#{} {} - {} at {} for {}h {:02}m",
+        template.id,
+        template.name,
+        template.recurrence,
+        template.start_time.format("%H:%M"),
+        template.duration_minutes / 60,
+        template.duration_minutes % 60
+    );
+    if !hosts.is_empty() {
+        let hosts: Vec<_> = hosts.iter().map(|host| format!("<@{}>", host.officer_id)).collect();
+        result += &format!(", hosted by {}", hosts.join(", "));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        // 2022-05-01 is a Sunday
+        chrono::NaiveDate::from_ymd(2022, 5, day)
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        let weekly: Recurrence = "Weekly thursday, mon".parse().unwrap();
+        assert_eq!(weekly, Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+        assert_eq!(weekly.to_string(), "weekly mon,thu");
+
+        let every: Recurrence = "every 3 days".parse().unwrap();
+        assert_eq!(every, Recurrence::EveryDays(3));
+        assert_eq!(every.to_string().parse::<Recurrence>().unwrap(), every);
+
+        let monthly: Recurrence = "monthly 2nd tue".parse().unwrap();
+        assert_eq!(monthly, Recurrence::Monthly { week: 2, weekday: Weekday::Tue });
+        assert_eq!(monthly.to_string(), "monthly 2 tue");
+        let last: Recurrence = "monthly last fri".parse().unwrap();
+        assert_eq!(last.to_string().parse::<Recurrence>().unwrap(), last);
+
+        assert!("weekly".parse::<Recurrence>().is_err());
+        assert!("weekly ,".parse::<Recurrence>().is_err());
+        assert!("weekly , ,".parse::<Recurrence>().is_err());
+        assert!("weekly mon,someday".parse::<Recurrence>().is_err());
+        assert!("every 0 days".parse::<Recurrence>().is_err());
+        assert!("monthly 6 tue".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_recurrence_dates() {
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]);
+        assert_eq!(weekly.dates(date(1), date(1), date(10)), vec![date(2), date(5), date(9)]);
+        // Nothing before the first day
+        assert_eq!(weekly.dates(date(6), date(1), date(10)), vec![date(9)]);
+
+        let every = Recurrence::EveryDays(3);
+        assert_eq!(every.dates(date(2), date(4), date(12)), vec![date(5), date(8), date(11)]);
+
+        let second_tuesday = Recurrence::Monthly { week: 2, weekday: Weekday::Tue };
+        assert_eq!(second_tuesday.dates(date(1), date(1), date(31)), vec![date(10)]);
+        let last_tuesday = Recurrence::Monthly { week: -1, weekday: Weekday::Tue };
+        assert_eq!(last_tuesday.dates(date(1), date(1), date(31)), vec![date(31)]);
+    }
+}
@@ -0,0 +1,200 @@
+use entity::loa;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+
+use crate::global::Error;
+use poise::serenity_prelude as serenity;
+
+use std::collections::HashSet;
+
+/// Request a leave of absence for an officer, it doesn't count until it has been approved.
+pub async fn request_loa(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+    reason: String,
+) -> Result<loa::Model, Error> {
+    if end < start {
+        return Err("A leave of absence can't end before it starts.".into());
+    }
+
+    use entity::sea_orm::entity::*;
+    let active_model = loa::ActiveModel {
+        officer_id: Set(user_id.0),
+        start: Set(start),
+        end: Set(end),
+        reason: Set(reason),
+        approved_by: Set(None),
+        ..Default::default()
+    };
+    Ok(active_model.insert(conn).await?)
+}
+
+/// Get a leave of absence by its id.
+pub async fn get_loa(conn: &DatabaseConnection, loa_id: i32) -> Result<loa::Model, Error> {
+    Ok(loa::Entity::find_by_id(loa_id)
+        .one(conn)
+        .await?
+        .ok_or(format!("There is no leave of absence with the id {}.", loa_id))?)
+}
+
+/// Approve a pending leave of absence.
+pub async fn approve_loa(
+    conn: &DatabaseConnection,
+    loa_id: i32,
+    approved_by: serenity::UserId,
+) -> Result<loa::Model, Error> {
+    let loa = get_loa(conn, loa_id).await?;
+    if loa.approved_by.is_some() {
+        return Err(format!("The leave of absence {} has already been approved.", loa_id).into());
+    }
+
+    use entity::sea_orm::entity::*;
+    let mut active_model: loa::ActiveModel = loa.into();
+    active_model.approved_by = Set(Some(approved_by.0));
+    Ok(active_model.update(conn).await?)
+}
+
+/// End a leave of absence on the day given.
+///
+/// Leaves that haven't started yet by then are removed instead. Returns the leave as it is now, or
+/// [`None`] if it was removed.
+pub async fn end_loa(
+    conn: &DatabaseConnection,
+    loa_id: i32,
+    today: chrono::NaiveDate,
+) -> Result<Option<loa::Model>, Error> {
+    let loa = get_loa(conn, loa_id).await?;
+    if loa.end < today {
+        return Err(format!("The leave of absence {} has already ended.", loa_id).into());
+    }
+
+    use entity::sea_orm::entity::*;
+    if loa.start >= today {
+        loa.delete(conn).await?;
+        return Ok(None);
+    }
+    let mut active_model: loa::ActiveModel = loa.into();
+    active_model.end = Set(today);
+    Ok(Some(active_model.update(conn).await?))
+}
+
+/// Get the leaves of absence that overlap with the from and to days, both of which are inclusive.
+///
+/// Only the leaves of the officer given are returned if there is one, pending leaves are left out
+/// unless they are asked for.
+pub async fn get_loas(
+    conn: &DatabaseConnection,
+    user_id: Option<serenity::UserId>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    include_pending: bool,
+) -> Result<Vec<loa::Model>, Error> {
+    let mut query = loa::Entity::find()
+        .filter(loa::Column::Start.lte(to))
+        .filter(loa::Column::End.gte(from))
+        .order_by_asc(loa::Column::Start);
+    if let Some(user_id) = user_id {
+        query = query.filter(loa::Column::OfficerId.eq(user_id.0));
+    }
+    if !include_pending {
+        query = query.filter(loa::Column::ApprovedBy.is_not_null());
+    }
+    Ok(query.all(conn).await?)
+}
+
+/// Get the leaves of absence that haven't ended yet on the day given, including pending ones.
+///
+/// Only the leaves of the officer given are returned if there is one.
+pub async fn get_current_loas(
+    conn: &DatabaseConnection,
+    user_id: Option<serenity::UserId>,
+    today: chrono::NaiveDate,
+) -> Result<Vec<loa::Model>, Error> {
+    let mut query =
+        loa::Entity::find().filter(loa::Column::End.gte(today)).order_by_asc(loa::Column::Start);
+    if let Some(user_id) = user_id {
+        query = query.filter(loa::Column::OfficerId.eq(user_id.0));
+    }
+    Ok(query.all(conn).await?)
+}
+
+/// Count the days between from and to, both inclusive, that are covered by the leaves given.
+///
+/// Days covered by more than one leave are only counted once.
+pub fn count_loa_days(loas: &[loa::Model], from: chrono::NaiveDate, to: chrono::NaiveDate) -> i64 {
+    let mut days = HashSet::new();
+    for loa in loas {
+        let mut day = loa.start.max(from);
+        while day <= loa.end.min(to) {
+            days.insert(day);
+            day = day.succ();
+        }
+    }
+    days.len() as i64
+}
+
+/// Leave the days an officer was on leave out of a patrol time requirement.
+///
+/// The requirement is lowered by the share of the window the officer was on leave, returns [`None`]
+/// if they were on leave for the whole window and nothing is required of them.
+pub fn scale_requirement(required_seconds: i64, window_days: i64, loa_days: i64) -> Option<i64> {
+    let active_days = window_days - loa_days;
+    match active_days > 0 && window_days > 0 {
+        true => Some(required_seconds * active_days / window_days),
+        false => None,
+    }
+}
+
+/// Describe a leave of absence on a single line.
+pub fn display_loa(loa: &loa::Model) -> String {
+    let status = match loa.approved_by {
+        Some(_) => "approved",
+        None => "pending",
+    };
+    format!("#{} {} to {} ({}): {}", loa.id, loa.start, loa.end, status, loa.reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loa(start: (u32, u32), end: (u32, u32)) -> loa::Model {
+        loa::Model {
+            id: 1,
+            officer_id: 1,
+            start: chrono::NaiveDate::from_ymd(2022, start.0, start.1),
+            end: chrono::NaiveDate::from_ymd(2022, end.0, end.1),
+            reason: "".to_owned(),
+            approved_by: Some(2),
+        }
+    }
+
+    #[test]
+    fn test_count_loa_days_clips_to_range() {
+        let from = chrono::NaiveDate::from_ymd(2022, 3, 1);
+        let to = chrono::NaiveDate::from_ymd(2022, 3, 31);
+        assert_eq!(count_loa_days(&[loa((2, 20), (3, 5))], from, to), 5);
+        assert_eq!(count_loa_days(&[loa((3, 30), (4, 10))], from, to), 2);
+        assert_eq!(count_loa_days(&[loa((2, 1), (2, 28))], from, to), 0);
+    }
+
+    #[test]
+    fn test_scale_requirement() {
+        assert_eq!(scale_requirement(28 * 3600, 28, 0), Some(28 * 3600));
+        assert_eq!(scale_requirement(28 * 3600, 28, 7), Some(21 * 3600));
+        assert_eq!(scale_requirement(28 * 3600, 28, 28), None);
+    }
+
+    #[test]
+    fn test_count_loa_days_overlapping_leaves() {
+        let from = chrono::NaiveDate::from_ymd(2022, 3, 1);
+        let to = chrono::NaiveDate::from_ymd(2022, 3, 31);
+        let loas = [loa((3, 1), (3, 10)), loa((3, 5), (3, 15))];
+        assert_eq!(count_loa_days(&loas, from, to), 15);
+    }
+}
@@ -1,9 +1,10 @@
+use entity::sea_orm::DatabaseConnection;
 use entity::sea_orm::EntityTrait;
 use entity::sea_orm::QueryFilter;
+use entity::sea_orm::TransactionTrait;
 use poise::serenity_prelude as serenity;
 
 use crate::config::CONFIG;
-use crate::db;
 use crate::global::{Data, Error, OfficerCache};
 
 use entity::officer;
@@ -30,6 +31,14 @@ pub async fn get_member_from_cache(
     officer_cache_map.get(&user_id.0).cloned()
 }
 
+/// Get everyone in the cache that is currently in the LPD.
+pub async fn get_active_officers(officer_cache: &OfficerCache) -> Vec<officer::Model> {
+    let officer_cache_lock = officer_cache.read().await;
+    let officer_cache_map = &*officer_cache_lock;
+
+    officer_cache_map.values().filter(|officer| officer.deleted_at.is_none()).cloned().collect()
+}
+
 // pub async fn is_in_cache_and<F>(
 //     officer_cache: &OfficerCache,
 //     user_id: &serenity::UserId,
@@ -55,6 +64,7 @@ pub async fn get_member_from_cache(
 // }
 
 async fn add_member(
+    conn: &DatabaseConnection,
     officer_cache: &OfficerCache,
     member: &Option<officer::Model>,
     user_id: &serenity::UserId,
@@ -81,28 +91,30 @@ async fn add_member(
         },
     };
 
-    // Add the user to the database
-    let connection = db::establish_connection().await;
+    // Add the user to the database, the insert and the fetch are done in one transaction so the
+    // cache gets the row that was actually written
+    let txn = conn.begin().await?;
     let in_cache = member.is_some();
     let model = match in_cache {
         true => {
             Officer::update(active_model)
                 .filter(officer::Column::Id.eq(user_id.0))
-                .exec(&connection)
+                .exec(&txn)
                 .await?
         }
         false => {
-            Officer::insert(active_model).exec(&connection).await?;
+            Officer::insert(active_model).exec(&txn).await?;
             // TODO: Change this into conversion when it is added in SeaORM:
             // https://github.com/SeaQL/sea-orm/issues/606
             Officer::find_by_id(user_id.0)
-                .one(&connection)
+                .one(&txn)
                 .await?
                 .ok_or("Officer not in database after they were added. The cache would have gotten out of sync.")?
         }
     };
+    txn.commit().await?;
 
-    // Add the new member to the cache
+    // Add the new member to the cache now that it is stored
     let mut officer_cache_lock = officer_cache.write().await;
     let officer_cache = &mut *officer_cache_lock;
     officer_cache.insert(user_id.0, model);
@@ -111,6 +123,7 @@ async fn add_member(
 }
 
 async fn remove_member(
+    conn: &DatabaseConnection,
     officer_cache: &OfficerCache,
     user_id: &serenity::UserId,
 ) -> Result<(), Error> {
@@ -123,9 +136,6 @@ async fn remove_member(
         .get_mut(&user_id.0)
         .ok_or("Officer removed from the cache between read and removal on member update.")?;
 
-    // Update in the cache
-    selected_officer.deleted_at = Some(deleted_at_date);
-
     // Create the update model
     use entity::sea_orm::entity::*;
     let active_model = officer::ActiveModel {
@@ -135,11 +145,12 @@ async fn remove_member(
     };
 
     // Update in the database
-    let connection = db::establish_connection().await;
-    Officer::update(active_model)
-        .filter(officer::Column::Id.eq(user_id.0))
-        .exec(&connection)
-        .await?;
+    let txn = conn.begin().await?;
+    Officer::update(active_model).filter(officer::Column::Id.eq(user_id.0)).exec(&txn).await?;
+    txn.commit().await?;
+
+    // Update in the cache now that it is stored
+    selected_officer.deleted_at = Some(deleted_at_date);
 
     Ok(())
 }
@@ -159,12 +170,10 @@ pub async fn event_listener(
             };
 
             // Add the user to the database if they just got an LPD role but aren't in the cache yet
-            // TODO: Change add_member and remove_member into transactions to allow for better error
-            // handling mid way through.
             if !in_cache_and_lpd && has_lpd_role(&new.roles) {
-                add_member(&user_data.officer_cache, &member, &new.user.id)
+                add_member(&user_data.db, &user_data.officer_cache, &member, &new.user.id)
                     .await
-                    .expect("Failed adding member on role change.");
+                    .map_err(|err| format!("Failed adding member on role change: {}", err))?;
                 println!(
                     "Added member {} ({}) ({}) as they just got the LPD role.",
                     &new.user, &new.user.name, &new.user.id
@@ -172,9 +181,9 @@ pub async fn event_listener(
             }
             // Remove an officer if they no longer have the LPD roles
             else if in_cache_and_lpd && !has_lpd_role(&new.roles) {
-                remove_member(&user_data.officer_cache, &new.user.id)
+                remove_member(&user_data.db, &user_data.officer_cache, &new.user.id)
                     .await
-                    .expect("Failed removing member on role change.");
+                    .map_err(|err| format!("Failed removing member on role change: {}", err))?;
                 println!(
                     "Removed member {} ({}) ({}) as they no longer have the LPD role.",
                     &new.user, &new.user.name, &new.user.id
@@ -182,9 +191,9 @@ pub async fn event_listener(
             };
         }
         poise::Event::GuildMemberRemoval { guild_id: _, user, member_data_if_available: _ } => {
-            remove_member(&user_data.officer_cache, &user.id)
+            remove_member(&user_data.db, &user_data.officer_cache, &user.id)
                 .await
-                .expect("Failed removing member on server leave.");
+                .map_err(|err| format!("Failed removing member on server leave: {}", err))?;
             println!(
                 "Removed member {} ({}) ({}) as they no longer have the LPD role.",
                 &user, &user.name, &user.id
@@ -196,13 +205,10 @@ pub async fn event_listener(
     Ok(())
 }
 
-pub async fn cache_init() -> OfficerCache {
+pub async fn cache_init(conn: &DatabaseConnection) -> OfficerCache {
     // Fill in the officer cache with all the officers from the database
-    let connection = db::establish_connection().await;
-    let officer_list = Officer::find()
-        .all(&connection)
-        .await
-        .expect("Couldn't fetch the officers from the database.");
+    let officer_list =
+        Officer::find().all(conn).await.expect("Couldn't fetch the officers from the database.");
     let officer_data: HashMap<_, _> = officer_list.into_iter().map(|m| (m.id, m)).collect();
     Arc::new(RwLock::new(officer_data))
 }
@@ -1,2 +1,12 @@
+pub mod activity_report;
+pub mod activity_warning;
+pub mod adjustment;
+pub mod claim;
+pub mod event;
+pub mod event_template;
+pub mod loa;
 pub mod member_management;
 pub mod patrol_measure;
+pub mod scheduled_event;
+pub mod status_board;
+pub mod voice_channel;
@@ -1,32 +1,140 @@
+use entity::active_patrol;
+use entity::active_patrol_mute;
+use entity::event;
+use entity::event_host;
+use entity::officer;
 use entity::patrol;
 use entity::patrol_voice;
+use entity::patrol_voice_mute;
 use entity::saved_voice_channel;
 
+use entity::sea_orm;
+use entity::sea_orm::sea_query::Expr;
 use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::ConnectionTrait;
+use entity::sea_orm::DatabaseConnection;
 use entity::sea_orm::EntityTrait;
+use entity::sea_orm::FromQueryResult;
+use entity::sea_orm::JoinType;
+use entity::sea_orm::PaginatorTrait;
 use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+use entity::sea_orm::QuerySelect;
+use entity::sea_orm::RelationTrait;
+use entity::sea_orm::TransactionTrait;
 
+use super::adjustment;
+use super::event::{find_patrol_event, ChannelSpan};
+use super::voice_channel;
 use crate::config::CONFIG;
-use crate::db;
 use crate::global::{Data, Error, PatrolCache};
-use migration::DbErr;
 use poise::serenity_prelude as serenity;
 use std::sync::Arc;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Whether an officer can hear and be heard in their voice channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceStatus {
+    Active,
+    Muted,
+    Deafened,
+}
+
+impl From<&serenity::VoiceState> for VoiceStatus {
+    fn from(voice_state: &serenity::VoiceState) -> Self {
+        if voice_state.deaf || voice_state.self_deaf {
+            VoiceStatus::Deafened
+        } else if voice_state.mute || voice_state.self_mute || voice_state.suppress {
+            VoiceStatus::Muted
+        } else {
+            VoiceStatus::Active
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
+pub struct MuteLog {
+    pub start: chrono::NaiveDateTime,
+    pub end: Option<chrono::NaiveDateTime>,
+    pub deafened: bool,
+}
+#[derive(Debug, Clone)]
 pub struct ChannelLog {
     pub guild_id: serenity::GuildId,
     pub channel_id: serenity::ChannelId,
     pub start: chrono::NaiveDateTime,
     pub end: Option<chrono::NaiveDateTime>,
+    pub mute_log: Vec<MuteLog>,
 }
+
+impl ChannelLog {
+    /// Get the status the officer has in this channel at the moment.
+    pub fn voice_status(&self) -> VoiceStatus {
+        match self.mute_log.last() {
+            Some(mute_log) if mute_log.end.is_none() && mute_log.deafened => VoiceStatus::Deafened,
+            Some(mute_log) if mute_log.end.is_none() => VoiceStatus::Muted,
+            _ => VoiceStatus::Active,
+        }
+    }
+
+    /// Change the status the officer has in this channel, ending the last mute log if it is open.
+    fn set_voice_status(&mut self, status: VoiceStatus, now: chrono::NaiveDateTime) {
+        if self.voice_status() == status {
+            return;
+        }
+        if let Some(mute_log) = self.mute_log.last_mut() {
+            mute_log.end = mute_log.end.or(Some(now));
+        }
+        match status {
+            VoiceStatus::Active => {}
+            VoiceStatus::Muted => {
+                self.mute_log.push(MuteLog { start: now, end: None, deafened: false })
+            }
+            VoiceStatus::Deafened => {
+                self.mute_log.push(MuteLog { start: now, end: None, deafened: true })
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PatrolLog {
     pub officer_id: serenity::UserId,
     pub voice_log: Vec<ChannelLog>,
+    /// The last time the bot knew this patrol was still going, only set for patrols recovered
+    /// from the database after a restart until they have been synced with the voice states.
+    pub recovered_until: Option<chrono::NaiveDateTime>,
+}
+
+/// An officer that is on duty at the moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnDuty {
+    pub officer_id: serenity::UserId,
+    pub channel_id: serenity::ChannelId,
+    /// When the patrol started
+    pub start: chrono::NaiveDateTime,
+    pub status: VoiceStatus,
+}
+
+impl PatrolLog {
+    /// Get where the officer is on duty, or [`None`] if they left and the patrol is waiting to be
+    /// closed.
+    pub fn on_duty(&self) -> Option<OnDuty> {
+        let first_log = self.voice_log.first()?;
+        let last_log = self.voice_log.last()?;
+        if last_log.end.is_some() {
+            return None;
+        }
+        Some(OnDuty {
+            officer_id: self.officer_id,
+            channel_id: last_log.channel_id,
+            start: first_log.start,
+            status: last_log.voice_status(),
+        })
+    }
 }
 
 #[inline]
@@ -34,57 +142,12 @@ fn no_voice_log_err(user_id: serenity::UserId) -> String {
     format!("There was an officer in the cache ({}) with no channel_logs, this shouldn't be possible as the minimum is always one.", user_id)
 }
 
-/// Get a saved voice channel or create one in the database if it doesn't exist.
-pub async fn get_saved_voice_channel(
-    guild_id: serenity::GuildId,
-    channel_id: serenity::ChannelId,
-) -> Result<saved_voice_channel::Model, Error> {
-    let conn = db::establish_connection().await;
-
-    // Try to get the channel
-    let try_get_channel_query = saved_voice_channel::Entity::find()
-        .filter(saved_voice_channel::Column::GuildId.eq(guild_id.0))
-        .filter(saved_voice_channel::Column::ChannelId.eq(channel_id.0));
-    let channel = try_get_channel_query.clone().one(&conn).await?;
-
-    match channel {
-        // Channel can just be returned as it existed already
-        Some(c) => Ok(c),
-        None => {
-            // The channel doesn't exist, create it instead
-            use entity::sea_orm::entity::*;
-            let active_model = saved_voice_channel::ActiveModel {
-                guild_id: Set(guild_id.0),
-                channel_id: Set(channel_id.0),
-                name: Set("".to_owned()),
-                ..Default::default()
-            };
-
-            // Try to get the model anyway if there is a DB error as it may just mean the channel
-            // has already been added by another thread
-            let save_result = active_model.save(&conn).await;
-            match save_result {
-                Ok(active_model) => Ok(
-                    // TODO: Change this into automatic conversion once that is added to SeaORM
-                    saved_voice_channel::Model {
-                        id: active_model.id.as_ref().to_owned(),
-                        guild_id: guild_id.0,
-                        channel_id: channel_id.0,
-                        name: "".to_owned()
-                    }
-                ),
-                Err(err) => match err {
-                    // Ignore the error if it failed inserting as it may have been because of
-                    // another thread, meaning it can still be fetched
-                    DbErr::Exec(_) => try_get_channel_query
-                        .one(&conn)
-                        .await?
-                        .ok_or_else(|| format!("Failed to insert into the database: \"{}\"\nThe saved voice channel still couldn't be found.", err).into()),
-                    _ => Err(err.into())
-                }
-            }
-        }
-    }
+/// Get everyone that is on duty at the moment, with the ones that have been on duty longest first.
+pub async fn get_on_duty(patrol_cache: &PatrolCache) -> Vec<OnDuty> {
+    let patrol_cache_lock = patrol_cache.read().await;
+    let mut on_duty: Vec<_> = patrol_cache_lock.values().filter_map(PatrolLog::on_duty).collect();
+    on_duty.sort_by_key(|item| item.start);
+    on_duty
 }
 
 /// Check if a officer is on patrol at the moment.
@@ -111,96 +174,597 @@ pub async fn is_on_patrol(
     }
 }
 
+/// Get the voice channel an officer is currently patrolling in according to the cache.
+///
+/// This function returns an error if the officer is in the cache but their patrol has no voice
+/// logs, the same as [`is_on_patrol`].
+async fn get_patrol_channel(
+    patrol_cache: &PatrolCache,
+    user_id: serenity::UserId,
+) -> Result<Option<serenity::ChannelId>, Error> {
+    // Get a read lock to the patrol cache
+    let patrol_cache_lock = patrol_cache.read().await;
+    let patrol_cache_map = &*patrol_cache_lock;
+
+    match patrol_cache_map.get(&user_id.0) {
+        Some(patrol_log) => Ok(Some(
+            patrol_log
+                .voice_log
+                .last()
+                .ok_or_else(|| Error::from(no_voice_log_err(user_id)))?
+                .channel_id,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Clip a time interval to the range given, both ends of the range are inclusive.
+///
+/// Returns [`None`] if the interval doesn't overlap with the range at all.
+fn clip_interval(
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Option<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+    match start <= to && end >= from {
+        true => Some((start.max(from), end.min(to))),
+        false => None,
+    }
+}
+
+/// Clip a patrol and its patrol_voice objects to the range given, leaving out the patrol_voice
+/// objects that are completely outside of it.
+fn clip_patrol(
+    (mut patrol, patrol_voices): (patrol::Model, Vec<patrol_voice::Model>),
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Option<(patrol::Model, Vec<patrol_voice::Model>)> {
+    (patrol.start, patrol.end) = clip_interval(patrol.start, patrol.end, from, to)?;
+    let patrol_voices = patrol_voices
+        .into_iter()
+        .filter_map(|mut pat_vc| {
+            (pat_vc.start, pat_vc.end) = clip_interval(pat_vc.start, pat_vc.end, from, to)?;
+            Some(pat_vc)
+        })
+        .collect();
+    Some((patrol, patrol_voices))
+}
+
+/// Get the patrols of an officer between the from and to times, both of which are inclusive.
+///
+/// Patrols that only partly overlap with that range are included but clipped to it, together with
+/// their patrol_voice objects, so only the time inside of the range is returned.
 pub async fn get_patrols(
+    conn: &DatabaseConnection,
     from: chrono::NaiveDateTime,
     to: chrono::NaiveDateTime,
     user_id: serenity::UserId,
 ) -> Result<Vec<(patrol::Model, Vec<patrol_voice::Model>)>, Error> {
-    let conn = db::establish_connection().await;
     // let conn = sea_orm::MockDatabase::new(sea_orm::DbBackend::MySql).into_connection();
     let result = patrol::Entity::find()
         .find_with_related(patrol_voice::Entity)
-        .filter(patrol::Column::Start.gt(from))
-        .filter(patrol::Column::End.lt(to))
+        .filter(patrol::Column::Start.lte(to))
+        .filter(patrol::Column::End.gte(from))
         .filter(patrol::Column::OfficerId.eq(user_id.0))
-        .all(&conn)
-        .await;
+        .filter(patrol::Column::Ignored.eq(false))
+        .all(conn)
+        .await?;
     // println!("Query: {:?}", conn.into_transaction_log());
-    Ok(result?)
+    Ok(result.into_iter().filter_map(|patrol| clip_patrol(patrol, from, to)).collect())
+}
+
+/// Get the mute and deafen intervals of some patrol_voice objects, grouped by their id.
+pub async fn get_patrol_voice_mutes(
+    conn: &DatabaseConnection,
+    patrol_voice_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<patrol_voice_mute::Model>>, Error> {
+    if patrol_voice_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mutes = patrol_voice_mute::Entity::find()
+        .filter(patrol_voice_mute::Column::PatrolVoiceId.is_in(patrol_voice_ids))
+        .all(conn)
+        .await?;
+
+    let mut result: HashMap<i32, Vec<patrol_voice_mute::Model>> = HashMap::new();
+    for mute in mutes {
+        result.entry(mute.patrol_voice_id).or_default().push(mute);
+    }
+    Ok(result)
+}
+
+/// Patrol time in seconds, split up by whether the officer was deafened or not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatrolDuration {
+    pub active: i64,
+    pub deafened: i64,
+    /// Time removed from part of a patrol by hand, which never counts
+    pub removed: i64,
 }
 
+impl PatrolDuration {
+    /// Get the time that counts towards the activity requirements according to the settings.
+    pub fn counted(&self) -> i64 {
+        let time = match CONFIG.patrol_time.count_deafened_time {
+            true => self.active + self.deafened,
+            false => self.active,
+        };
+        time - self.removed
+    }
+}
+
+/// Get the patrol time of an officer between the from and to times, both of which are inclusive.
+///
+/// Only the time inside of that range is counted, the same as in [`get_patrols`].
 pub async fn get_patrol_time(
+    conn: &DatabaseConnection,
     from: chrono::NaiveDateTime,
     to: chrono::NaiveDateTime,
     user_id: serenity::UserId,
-) -> Result<i64, Error> {
-    let patrols = get_patrols(from, to, user_id).await?;
+) -> Result<PatrolDuration, Error> {
+    let patrols = get_patrols(conn, from, to, user_id).await?;
     let patrol_time = patrols
         .iter()
         .fold(0, |acc, item| acc + item.0.end.signed_duration_since(item.0.start).num_seconds());
-    Ok(patrol_time)
+
+    // Take the time the officer spent deafened out of the active time
+    let patrol_voice_ids = patrols.iter().flat_map(|item| item.1.iter().map(|pat_vc| pat_vc.id));
+    let mutes = get_patrol_voice_mutes(conn, patrol_voice_ids.collect()).await?;
+    let deafened_time = mutes
+        .values()
+        .flatten()
+        .filter(|mute| mute.deafened)
+        .filter_map(|mute| clip_interval(mute.start, mute.end, from, to))
+        .fold(0, |acc, (start, end)| acc + end.signed_duration_since(start).num_seconds());
+
+    // Time removed from part of a patrol by hand is kept apart
+    let removed = adjustment::get_removed_time(conn, from, to, Some(user_id)).await?;
+    let removed = removed.get(&user_id.0).copied().unwrap_or(0);
+
+    Ok(PatrolDuration { active: patrol_time - deafened_time, deafened: deafened_time, removed })
+}
+
+/// Add up the time of patrol_voice objects per saved voice channel id.
+fn sum_channel_times<'a>(
+    patrol_voices: impl IntoIterator<Item = &'a patrol_voice::Model>,
+) -> HashMap<i32, i64> {
+    let mut result = HashMap::new();
+    for pat_vc in patrol_voices {
+        *result.entry(pat_vc.channel_id).or_default() +=
+            pat_vc.end.signed_duration_since(pat_vc.start).num_seconds();
+    }
+    result
+}
+
+/// Get the patrol time of an officer per voice channel between the from and to times, both of
+/// which are inclusive, with the channel they spent the most time in first.
+///
+/// This is all the time spent in each channel, time spent deafened isn't taken out of it.
+pub async fn get_channel_breakdown(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    user_id: serenity::UserId,
+) -> Result<Vec<(saved_voice_channel::Model, i64)>, Error> {
+    let patrols = get_patrols(conn, from, to, user_id).await?;
+    let channel_times = sum_channel_times(patrols.iter().flat_map(|item| item.1.iter()));
+    if channel_times.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let channels = saved_voice_channel::Entity::find()
+        .filter(saved_voice_channel::Column::Id.is_in(channel_times.keys().copied()))
+        .all(conn)
+        .await?;
+    let mut result: Vec<_> = channels
+        .into_iter()
+        .map(|channel| {
+            let time = channel_times.get(&channel.id).copied().unwrap_or(0);
+            (channel, time)
+        })
+        .collect();
+    result.sort_by_key(|item| std::cmp::Reverse(item.1));
+    Ok(result)
+}
+
+/// The total patrol time of an officer, as ranked on the leaderboard.
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct LeaderboardEntry {
+    pub officer_id: u64,
+    /// Patrol time in seconds
+    pub patrol_time: i64,
+}
+
+/// The time an officer spent deafened on patrol, as summed up for the leaderboard.
+#[derive(Debug, Clone, FromQueryResult)]
+struct DeafenedTime {
+    officer_id: u64,
+    /// Deafened time in seconds
+    deafened_time: i64,
+}
+
+/// The time an officer spent hosting events while they weren't on patrol, as summed up for the
+/// leaderboard.
+#[derive(Debug, Clone, FromQueryResult)]
+struct HostTime {
+    officer_id: u64,
+    /// Host time in seconds
+    host_time: i64,
+}
+
+/// Sort leaderboard entries with the most time first, officers with the same time are ordered by
+/// their id so the ranking doesn't change between pages.
+fn rank_leaderboard(entries: &mut [LeaderboardEntry]) {
+    entries.sort_by_key(|entry| (std::cmp::Reverse(entry.patrol_time), entry.officer_id));
+}
+
+/// Take time that doesn't count, like the time spent deafened, out of each leaderboard entry.
+fn subtract_time(entries: &mut [LeaderboardEntry], times: &HashMap<u64, i64>) {
+    for entry in entries.iter_mut() {
+        entry.patrol_time -= times.get(&entry.officer_id).copied().unwrap_or(0);
+    }
+}
+
+/// Add the time each officer hosted events to their leaderboard entry, officers that only hosted
+/// get an entry of their own.
+fn add_host_time(entries: &mut Vec<LeaderboardEntry>, host_time: &HashMap<u64, i64>) {
+    for (officer_id, host_time) in host_time {
+        match entries.iter_mut().find(|entry| entry.officer_id == *officer_id) {
+            Some(entry) => entry.patrol_time += host_time,
+            None if *host_time > 0 => {
+                entries.push(LeaderboardEntry { officer_id: *officer_id, patrol_time: *host_time })
+            }
+            None => {}
+        }
+    }
+}
+
+/// Rank the officers that are currently in the LPD by their patrol time between the from and to
+/// times, with the most time first.
+///
+/// Both times are inclusive and patrols overlapping the edges are clipped the same as in
+/// [`get_patrols`], this is done in grouped queries instead of queries per officer. Time removed
+/// by hand is taken out, as is time spent deafened unless it counts according to the settings, the
+/// same as in [`PatrolDuration::counted`]. The time officers hosted events off patrol is added if
+/// that is turned on, the same as in [`event::get_counted_time`].
+///
+/// [`event::get_counted_time`]: super::event::get_counted_time
+pub async fn get_leaderboard(
+    conn: &DatabaseConnection,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<Vec<LeaderboardEntry>, Error> {
+    let patrol_time = Expr::cust_with_values(
+        "CAST(SUM(TIMESTAMPDIFF(SECOND, GREATEST(`patrols`.`start`, ?), LEAST(`patrols`.`end`, ?))) AS SIGNED)",
+        vec![from, to],
+    );
+    let mut result = patrol::Entity::find()
+        .select_only()
+        .column(patrol::Column::OfficerId)
+        .column_as(patrol_time, "patrol_time")
+        .join(JoinType::InnerJoin, patrol::Relation::Officer.def())
+        .filter(officer::Column::DeletedAt.is_null())
+        .filter(patrol::Column::Start.lte(to))
+        .filter(patrol::Column::End.gte(from))
+        .filter(patrol::Column::Ignored.eq(false))
+        .group_by(patrol::Column::OfficerId)
+        .into_model::<LeaderboardEntry>()
+        .all(conn)
+        .await?;
+
+    if !CONFIG.patrol_time.count_deafened_time {
+        let deafened_time = Expr::cust_with_values(
+            "CAST(SUM(TIMESTAMPDIFF(SECOND, GREATEST(`patrol_voice_mutes`.`start`, ?), LEAST(`patrol_voice_mutes`.`end`, ?))) AS SIGNED)",
+            vec![from, to],
+        );
+        let deafened: HashMap<_, _> = patrol_voice_mute::Entity::find()
+            .select_only()
+            .column(patrol::Column::OfficerId)
+            .column_as(deafened_time, "deafened_time")
+            .join(JoinType::InnerJoin, patrol_voice_mute::Relation::PatrolVoice.def())
+            .join(JoinType::InnerJoin, patrol_voice::Relation::Patrol.def())
+            .join(JoinType::InnerJoin, patrol::Relation::Officer.def())
+            .filter(officer::Column::DeletedAt.is_null())
+            .filter(patrol::Column::Ignored.eq(false))
+            .filter(patrol_voice_mute::Column::Deafened.eq(true))
+            .filter(patrol_voice_mute::Column::Start.lte(to))
+            .filter(patrol_voice_mute::Column::End.gte(from))
+            .group_by(patrol::Column::OfficerId)
+            .into_model::<DeafenedTime>()
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|row| (row.officer_id, row.deafened_time))
+            .collect();
+        subtract_time(&mut result, &deafened);
+    }
+
+    let removed = adjustment::get_removed_time(conn, from, to, None).await?;
+    subtract_time(&mut result, &removed);
+
+    if CONFIG.events.count_host_time {
+        // The length of each hosted event minus the part of it the host spent on patrol, patrols
+        // of an officer don't overlap so their overlaps with the event can be added up
+        let host_time = Expr::cust_with_values(
+            "CAST(SUM(TIMESTAMPDIFF(SECOND, GREATEST(`events`.`start`, ?), LEAST(`events`.`end`, ?)) - COALESCE((\
+                SELECT SUM(TIMESTAMPDIFF(SECOND, GREATEST(`p`.`start`, `events`.`start`, ?), LEAST(`p`.`end`, `events`.`end`, ?))) \
+                FROM `patrols` AS `p` \
+                WHERE `p`.`officer_id` = `event_hosts`.`officer_id` AND `p`.`ignored` = FALSE \
+                AND `p`.`start` < LEAST(`events`.`end`, ?) AND `p`.`end` > GREATEST(`events`.`start`, ?)\
+            ), 0)) AS SIGNED)",
+            vec![from, to, from, to, to, from],
+        );
+        let hosted: HashMap<_, _> = event_host::Entity::find()
+            .select_only()
+            .column(event_host::Column::OfficerId)
+            .column_as(host_time, "host_time")
+            .join(JoinType::InnerJoin, event_host::Relation::Event.def())
+            .join(JoinType::InnerJoin, event_host::Relation::Officer.def())
+            .filter(officer::Column::DeletedAt.is_null())
+            .filter(event::Column::Start.lt(to))
+            .filter(event::Column::End.gt(from))
+            .filter(event::Column::CancelledAt.is_null())
+            .group_by(event_host::Column::OfficerId)
+            .into_model::<HostTime>()
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|row| (row.officer_id, row.host_time))
+            .collect();
+        add_host_time(&mut result, &hosted);
+    }
+
+    rank_leaderboard(&mut result);
+    Ok(result)
+}
+
+/// Get the time the last counted patrol of an officer ended, if they have ever patrolled.
+pub async fn get_last_patrol_end(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+) -> Result<Option<chrono::NaiveDateTime>, Error> {
+    let last_patrol = patrol::Entity::find()
+        .filter(patrol::Column::OfficerId.eq(user_id.0))
+        .filter(patrol::Column::Ignored.eq(false))
+        .order_by_desc(patrol::Column::End)
+        .one(conn)
+        .await?;
+    Ok(last_patrol.map(|patrol| patrol.end))
+}
+
+/// Look for data in the database that doesn't add up, returning a description of each problem.
+///
+/// This checks for patrols that end before they start, patrol_voice objects that are outside of
+/// their patrol and active patrols that are out of sync with the patrol cache.
+pub async fn check_consistency(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+) -> Result<Vec<String>, Error> {
+    let mut problems = Vec::new();
+
+    let backwards_patrols = patrol::Entity::find()
+        .filter(Expr::cust("`patrols`.`end` < `patrols`.`start`"))
+        .count(conn)
+        .await?;
+    if backwards_patrols > 0 {
+        problems.push(format!("{} patrols end before they start", backwards_patrols));
+    }
+
+    let stray_patrol_voices = patrol_voice::Entity::find()
+        .join(JoinType::InnerJoin, patrol_voice::Relation::Patrol.def())
+        .filter(Expr::cust(
+            "`patrol_voice_comms`.`start` < `patrols`.`start` OR `patrol_voice_comms`.`end` > `patrols`.`end`",
+        ))
+        .count(conn)
+        .await?;
+    if stray_patrol_voices > 0 {
+        problems
+            .push(format!("{} patrol voice logs are outside of their patrol", stray_patrol_voices));
+    }
+
+    // Every patrol in the cache should be written through to the active patrols and the other way
+    // around, the read lock keeps the patrols from changing in between
+    let patrol_cache_lock = patrol_cache.read().await;
+    let cached_officers: HashSet<_> = patrol_cache_lock.keys().copied().collect();
+    let active_officers: HashSet<_> = active_patrol::Entity::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|active_log| active_log.officer_id)
+        .collect();
+    drop(patrol_cache_lock);
+    for officer_id in active_officers.difference(&cached_officers) {
+        problems.push(format!(
+            "{} has an active patrol in the database but not in the cache",
+            officer_id
+        ));
+    }
+    for officer_id in cached_officers.difference(&active_officers) {
+        problems.push(format!("{} has a patrol in the cache but not in the database", officer_id));
+    }
+
+    Ok(problems)
 }
 
 /// Get the main channel for some officers voice_logs.
 ///
+/// This is the channel the officer spent the most time in, leaving out channels with a name that
+/// starts with one of the bad_main_channel_starts in the settings. If all the channels are bad the
+/// channel with the most time overall is used instead. Voice logs that are still open count as
+/// ending at the time given and channels without a name are never seen as bad.
+///
 /// This function returns an error if there are no voice logs as everyone should always have at
 /// least 1.
-async fn get_main_channel(
-    discord_cache: &Arc<serenity::Cache>,
+fn get_main_channel<F>(
     voice_logs: &[ChannelLog],
-) -> Result<serenity::ChannelId, Error> {
-    // Loop through each voice log and see if it is in a channel that can be a main channel
-    let main_channel = voice_logs.iter().find(|voice_log| {
-        // Get the name of the current channel
-        let some_name = discord_cache.guild_channel_field(voice_log.channel_id, |c| c.name.clone());
-        // Check if the name of this channel can be a main channel according to the settings
-        match some_name {
-            Some(name) => CONFIG
-                .patrol_time
-                .bad_main_channel_starts
-                .iter()
-                .any(|start| name.starts_with(start.as_str())),
-            None => false,
+    end: chrono::NaiveDateTime,
+    get_channel_name: F,
+) -> Result<serenity::ChannelId, Error>
+where
+    F: Fn(serenity::ChannelId) -> Option<String>,
+{
+    // Add up the time spent in each channel, keeping the order they were first joined in
+    let mut channel_times: Vec<(serenity::ChannelId, i64)> = Vec::new();
+    for voice_log in voice_logs {
+        let duration = voice_log.end.unwrap_or(end).signed_duration_since(voice_log.start);
+        match channel_times.iter_mut().find(|(channel_id, _)| *channel_id == voice_log.channel_id) {
+            Some((_, time)) => *time += duration.num_seconds(),
+            None => channel_times.push((voice_log.channel_id, duration.num_seconds())),
         }
-    });
+    }
+
+    // Check if the name of a channel means it can't be a main channel according to the settings
+    let is_bad_channel = |channel_id: serenity::ChannelId| match get_channel_name(channel_id) {
+        Some(name) => CONFIG
+            .patrol_time
+            .bad_main_channel_starts
+            .iter()
+            .any(|start| name.starts_with(start.as_str())),
+        None => false,
+    };
+
+    // Find the longest good channel, falling back to the longest channel if none are good. The
+    // iterators are reversed so the channel that was joined first wins if there is a tie.
+    let main_channel = channel_times
+        .iter()
+        .rev()
+        .filter(|(channel_id, _)| !is_bad_channel(*channel_id))
+        .max_by_key(|(_, time)| *time)
+        .or_else(|| channel_times.iter().rev().max_by_key(|(_, time)| *time));
 
-    // Give the current channel if no channel was found.
     match main_channel {
-        Some(channel_log) => Ok(channel_log.channel_id),
-        None => Ok(voice_logs
-            .last()
-            .ok_or_else(|| Error::from(no_voice_log_err(serenity::UserId { 0: 0 })))?
-            .channel_id),
+        Some((channel_id, _)) => Ok(*channel_id),
+        None => Err(no_voice_log_err(serenity::UserId(0)).into()),
     }
 }
 
+/// Write a new voice log through to the active patrols in the database.
+async fn save_active_channel_log(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    channel_log: &ChannelLog,
+) -> Result<(), Error> {
+    use entity::sea_orm::entity::*;
+    let active_model = active_patrol::ActiveModel {
+        officer_id: Set(user_id.0),
+        guild_id: Set(channel_log.guild_id.0),
+        channel_id: Set(channel_log.channel_id.0),
+        start: Set(channel_log.start),
+        end: Set(channel_log.end),
+        last_seen: Set(channel_log.start),
+        ..Default::default()
+    };
+    active_model.insert(conn).await?;
+    Ok(())
+}
+
+/// End the open voice log of an officer in the active patrols in the database.
+async fn end_active_channel_log(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    end: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    active_patrol::Entity::update_many()
+        .col_expr(active_patrol::Column::End, Expr::value(end))
+        .filter(active_patrol::Column::OfficerId.eq(user_id.0))
+        .filter(active_patrol::Column::End.is_null())
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Open the voice log given back up in the active patrols in the database.
+async fn reopen_active_channel_log(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    channel_log: &ChannelLog,
+) -> Result<(), Error> {
+    active_patrol::Entity::update_many()
+        .col_expr(active_patrol::Column::End, Expr::value(Option::<chrono::NaiveDateTime>::None))
+        .filter(active_patrol::Column::OfficerId.eq(user_id.0))
+        .filter(active_patrol::Column::ChannelId.eq(channel_log.channel_id.0))
+        .filter(active_patrol::Column::Start.eq(channel_log.start))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
+/// Write a change in the voice status of an officer through to the database.
+///
+/// This ends the open mute log of the officer and starts a new one if they are muted or deafened.
+async fn save_active_voice_status(
+    conn: &DatabaseConnection,
+    user_id: serenity::UserId,
+    status: VoiceStatus,
+    now: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    active_patrol_mute::Entity::update_many()
+        .col_expr(active_patrol_mute::Column::End, Expr::value(now))
+        .filter(active_patrol_mute::Column::OfficerId.eq(user_id.0))
+        .filter(active_patrol_mute::Column::End.is_null())
+        .exec(conn)
+        .await?;
+
+    let deafened = match status {
+        VoiceStatus::Active => return Ok(()),
+        VoiceStatus::Muted => false,
+        VoiceStatus::Deafened => true,
+    };
+    use entity::sea_orm::entity::*;
+    let active_model = active_patrol_mute::ActiveModel {
+        officer_id: Set(user_id.0),
+        start: Set(now),
+        end: Set(None),
+        deafened: Set(deafened),
+        ..Default::default()
+    };
+    active_model.insert(conn).await?;
+    Ok(())
+}
+
+/// Remove all the voice logs of an officer from the active patrols in the database.
+async fn clear_active_patrol<C: ConnectionTrait>(
+    conn: &C,
+    user_id: serenity::UserId,
+) -> Result<(), Error> {
+    active_patrol::Entity::delete_many()
+        .filter(active_patrol::Column::OfficerId.eq(user_id.0))
+        .exec(conn)
+        .await?;
+    active_patrol_mute::Entity::delete_many()
+        .filter(active_patrol_mute::Column::OfficerId.eq(user_id.0))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
+
 /// Register a user going on duty
 async fn go_on_duty(
+    conn: &DatabaseConnection,
     patrol_cache: &PatrolCache,
     user_id: serenity::UserId,
     guild_id: serenity::GuildId,
     channel_id: serenity::ChannelId,
+    status: VoiceStatus,
 ) -> Result<(), Error> {
-    // Make sure we don't keep the lock longer than we need to
-    let return_value = {
-        // Get a write lock to the cache
-        let mut patrol_cache_lock = patrol_cache.write().await;
-        let patrol_cache_map = &mut *patrol_cache_lock;
-
-        // Add the patrol to the cache
-        patrol_cache_map.insert(
-            user_id.0,
-            PatrolLog {
-                officer_id: user_id,
-                voice_log: vec![ChannelLog {
-                    guild_id,
-                    channel_id,
-                    start: chrono::Utc::now().naive_utc(),
-                    end: None,
-                }],
-            },
-        )
-    };
+    let now = chrono::Utc::now().naive_utc();
+    let mut channel_log =
+        ChannelLog { guild_id, channel_id, start: now, end: None, mute_log: Vec::new() };
+    channel_log.set_voice_status(status, now);
+
+    // Write the patrol through to the database so it survives the bot crashing, replacing
+    // whatever is left of a patrol that would be dropped below
+    clear_active_patrol(conn, user_id).await?;
+    save_active_channel_log(conn, user_id, &channel_log).await?;
+    save_active_voice_status(conn, user_id, status, now).await?;
+
+    // Add the patrol to the cache, only taking the write lock once the database is up to date
+    let return_value = patrol_cache.write().await.insert(
+        user_id.0,
+        PatrolLog { officer_id: user_id, voice_log: vec![channel_log], recovered_until: None },
+    );
 
     // Throw an error if the user already existed in the cache
     match return_value {
@@ -213,18 +777,27 @@ async fn go_on_duty(
     }
 }
 
+/// Create the model for a voice log of a patrol, the patrol id is filled in once the patrol itself
+/// has been saved.
 async fn create_patrol_voice(
-    patrol_id: i32,
+    conn: &DatabaseConnection,
+    discord_cache: &serenity::Cache,
     patrol_voice: &ChannelLog,
+    patrol_end: chrono::NaiveDateTime,
 ) -> Result<patrol_voice::ActiveModel, Error> {
-    let channel = get_saved_voice_channel(CONFIG.guild_id.into(), patrol_voice.channel_id).await?;
+    let channel = voice_channel::get_saved_voice_channel(
+        conn,
+        patrol_voice.guild_id,
+        patrol_voice.channel_id,
+        discord_cache.guild_channel(patrol_voice.channel_id),
+    )
+    .await?;
     let end = match patrol_voice.end {
         Some(val) => val,
-        None => chrono::Utc::now().naive_utc(),
+        None => patrol_end,
     };
     use entity::sea_orm::entity::*;
     Ok(patrol_voice::ActiveModel {
-        patrol_id: Set(patrol_id),
         channel_id: Set(channel.id),
         start: Set(patrol_voice.start),
         end: Set(end),
@@ -232,101 +805,397 @@ async fn create_patrol_voice(
     })
 }
 
-/// Register a user going off duty
+/// Remove the voice logs that are shorter than the minimum given.
+///
+/// Voice logs in the same channel that end up next to each other after that are merged back into
+/// one. The longest voice log is kept if all of them are too short so there is always at least
+/// one left. Voice logs that are still open are counted as ending at the time given.
+fn remove_short_channel_logs(
+    voice_log: &[ChannelLog],
+    end: chrono::NaiveDateTime,
+    min_seconds: i64,
+) -> Vec<ChannelLog> {
+    let duration = |log: &ChannelLog| log.end.unwrap_or(end).signed_duration_since(log.start);
+
+    let mut result: Vec<ChannelLog> = Vec::with_capacity(voice_log.len());
+    for channel_log in voice_log.iter().filter(|log| duration(log).num_seconds() >= min_seconds) {
+        match result.last_mut() {
+            Some(last_log) if last_log.channel_id == channel_log.channel_id => {
+                last_log.end = channel_log.end;
+                last_log.mute_log.extend_from_slice(&channel_log.mute_log);
+            }
+            _ => result.push(channel_log.clone()),
+        }
+    }
+
+    // Never drop every single voice log
+    if result.is_empty() {
+        if let Some(longest_log) = voice_log.iter().max_by_key(|log| duration(log)) {
+            result.push(longest_log.clone());
+        }
+    }
+
+    result
+}
+
+/// Write a finished patrol to the database.
+///
+/// Voice logs that are still open are ended at the time given, if the officer already left comms
+/// the patrol ends when they left instead. Patrols shorter than the minimum in the settings are
+/// saved as ignored so they don't count towards anyone's patrol time.
+async fn save_patrol(
+    conn: &DatabaseConnection,
+    discord_cache: &Arc<serenity::Cache>,
+    patrol_log: &PatrolLog,
+    end: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    let user_id = patrol_log.officer_id;
+    let last_log =
+        patrol_log.voice_log.last().ok_or_else(|| Error::from(no_voice_log_err(user_id)))?;
+    let end = last_log.end.unwrap_or(end);
+
+    // Get the main channel
+    let main_channel_discord_id = get_main_channel(&patrol_log.voice_log, end, |channel_id| {
+        discord_cache.guild_channel_field(channel_id, |c| c.name.clone())
+    })?;
+    let main_channel = voice_channel::get_saved_voice_channel(
+        conn,
+        CONFIG.guild_id.into(),
+        main_channel_discord_id,
+        discord_cache.guild_channel(main_channel_discord_id),
+    )
+    .await?;
+
+    // Check if the patrol was long enough to count
+    let start =
+        patrol_log.voice_log.first().ok_or_else(|| Error::from(no_voice_log_err(user_id)))?.start;
+    let ignored =
+        end.signed_duration_since(start).num_seconds() < CONFIG.patrol_time.min_patrol_seconds;
+
+    // Create the models for the data
+    use entity::sea_orm::entity::*;
+    let mut model = patrol::ActiveModel {
+        officer_id: Set(user_id.0),
+        main_channel_id: Set(Some(main_channel.id)),
+        start: Set(start),
+        end: Set(end),
+        event_id: Set(None),
+        ignored: Set(ignored),
+        manual_by: Set(None),
+        reason: Set(None),
+        ..Default::default()
+    };
+
+    // Create the patrol_voice models, leaving out quick channel hops. The channels are looked up
+    // outside of the transaction as a failed insert there, for a channel another thread just added,
+    // would abort the whole transaction.
+    let voice_log = remove_short_channel_logs(
+        &patrol_log.voice_log,
+        end,
+        CONFIG.patrol_time.min_segment_seconds,
+    );
+    let pat_vc_futures =
+        voice_log.iter().map(|ch_log| create_patrol_voice(conn, discord_cache, ch_log, end));
+    let patrol_voice_models = futures::future::try_join_all(pat_vc_futures).await?;
+
+    // Link the patrol to the event it spent the most time in, if it was part of one
+    let spans: Vec<_> = patrol_voice_models
+        .iter()
+        .zip(&voice_log)
+        .map(|(patrol_voice_model, channel_log)| ChannelSpan {
+            channel_id: patrol_voice_model.channel_id.clone().unwrap(),
+            start: channel_log.start,
+            end: channel_log.end.unwrap_or(end),
+        })
+        .collect();
+    let event = find_patrol_event(conn, &spans).await?;
+    model.event_id = Set(event.map(|event| event.id));
+
+    // Save everything in one transaction so a patrol is never stored with missing voice logs
+    let txn = conn.begin().await?;
+    let patrol_id = model.insert(&txn).await?.id;
+    for (mut patrol_voice_model, channel_log) in patrol_voice_models.into_iter().zip(&voice_log) {
+        patrol_voice_model.patrol_id = Set(patrol_id);
+        let patrol_voice_id = patrol_voice_model.insert(&txn).await?.id;
+
+        // Save the times the officer was muted or deafened in this patrol_voice
+        let channel_end = channel_log.end.unwrap_or(end);
+        for mute_log in &channel_log.mute_log {
+            let mute_model = patrol_voice_mute::ActiveModel {
+                patrol_voice_id: Set(patrol_voice_id),
+                start: Set(mute_log.start),
+                end: Set(mute_log.end.unwrap_or(channel_end)),
+                deafened: Set(mute_log.deafened),
+                ..Default::default()
+            };
+            mute_model.insert(&txn).await?;
+        }
+    }
+
+    // The patrol is stored with the commit, it no longer needs to be recovered after a crash
+    clear_active_patrol(&txn, user_id).await?;
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Register a user going off duty, ending their patrol at the time given
 async fn go_off_duty(
+    conn: &DatabaseConnection,
     patrol_cache: &PatrolCache,
     discord_cache: &Arc<serenity::Cache>,
     user_id: serenity::UserId,
+    end: chrono::NaiveDateTime,
 ) -> Result<(), Error> {
-    // Write the results to the database
-    {
-        // Get a read lock to the patrol cache
-        let patrol_cache_lock = patrol_cache.read().await;
-        let patrol_cache_map = &*patrol_cache_lock;
+    // Copy the patrol log for specified officer so the cache isn't locked while saving
+    let patrol_log = patrol_cache
+        .read()
+        .await
+        .get(&user_id.0)
+        .cloned()
+        .ok_or(format!("Officer not on duty ({}) but tried to go off duty.", user_id))?;
 
-        // Get the patrol log for specified officer
-        let now = chrono::Utc::now().naive_utc();
-        let patrol_log = patrol_cache_map.get(&user_id.0).ok_or(format!(
-            "Officer not on duty ({}) but tried to move from one on duty VC to another one.",
-            user_id
-        ))?;
-
-        // Get the main channel
-        let main_channel_discord_id =
-            get_main_channel(discord_cache, &patrol_log.voice_log).await?;
-        let main_channel =
-            get_saved_voice_channel(CONFIG.guild_id.into(), main_channel_discord_id).await?;
-
-        // Create the models for the data
-        use entity::sea_orm::entity::*;
-        let model = patrol::ActiveModel {
-            officer_id: Set(user_id.0),
-            main_channel_id: Set(main_channel.id),
-            start: Set(patrol_log
-                .voice_log
-                .first()
-                .ok_or_else(|| Error::from(no_voice_log_err(user_id)))?
-                .start),
-            end: Set(now),
-            event_id: Set(None),
-            ..Default::default()
-        };
+    // Write the results to the database and remove the patrol from the cache
+    save_patrol(conn, discord_cache, &patrol_log, end).await?;
+    patrol_cache.write().await.remove(&user_id.0);
+
+    Ok(())
+}
+
+/// Register a user leaving on duty comms.
+///
+/// The patrol is kept as pending close for the grace period in the settings, if the officer
+/// rejoins before that it continues through [`move_on_duty_vc`] instead of starting a new one.
+async fn leave_on_duty_vc(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    discord_cache: &Arc<serenity::Cache>,
+    user_id: serenity::UserId,
+) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let left_at = mark_left_on_duty_vc(conn, patrol_cache, user_id, now).await?;
 
-        // Save the data to the database
-        let conn = db::establish_connection().await;
-        let saved_model = model.save(&conn).await?;
+    // Close the patrol if the officer doesn't come back within the grace period
+    let grace_period = Duration::from_secs(CONFIG.patrol_time.disconnect_grace_seconds);
+    spawn_patrol_closer(conn, patrol_cache, discord_cache, user_id, left_at, grace_period);
 
-        // Create the patrol_voice models
-        let patrol_id = saved_model.id.as_ref().to_owned();
-        let create_pat_vc =
-            |ch_log| Box::pin(async move { create_patrol_voice(patrol_id, ch_log).await });
-        let pat_vc_futures = patrol_log.voice_log.iter().map(create_pat_vc);
-        let patrol_voice_models = futures::future::try_join_all(pat_vc_futures).await?;
+    Ok(())
+}
 
-        // Save the patrol_voices
-        futures::future::try_join_all(
-            patrol_voice_models.into_iter().map(|model| model.save(&conn)),
-        )
-        .await?;
+/// End the last voice log of an officer at the time given, marking the patrol as pending close.
+///
+/// Returns the time the officer left comms, which is the earlier time if they already left.
+async fn mark_left_on_duty_vc(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    user_id: serenity::UserId,
+    now: chrono::NaiveDateTime,
+) -> Result<chrono::NaiveDateTime, Error> {
+    // Check if the officer already left, without keeping the lock during the database writes
+    let left_at = patrol_cache
+        .read()
+        .await
+        .get(&user_id.0)
+        .ok_or(format!("Officer not on duty ({}) but tried to leave on duty VC.", user_id))?
+        .voice_log
+        .last()
+        .ok_or_else(|| Error::from(no_voice_log_err(user_id)))?
+        .end;
+    if let Some(left_at) = left_at {
+        return Ok(left_at);
     }
 
-    // Remove the patrol from the cache
+    // Write the leave through to the database first so the cache only changes if that worked
+    end_active_channel_log(conn, user_id, now).await?;
+    save_active_voice_status(conn, user_id, VoiceStatus::Active, now).await?;
+
+    // End the last VC time
+    if let Some(last_log) = patrol_cache
+        .write()
+        .await
+        .get_mut(&user_id.0)
+        .and_then(|patrol_log| patrol_log.voice_log.last_mut())
     {
-        // Get a write lock to the cache
-        let mut patrol_cache_lock = patrol_cache.write().await;
-        let patrol_cache_map = &mut *patrol_cache_lock;
+        last_log.set_voice_status(VoiceStatus::Active, now);
+        last_log.end = last_log.end.or(Some(now));
+    }
 
-        // Remove the value
-        patrol_cache_map.remove(&user_id.0);
+    Ok(now)
+}
+
+/// Close a pending patrol after the delay given, retrying if writing it to the database fails.
+///
+/// A patrol that still can't be saved after the retries in the settings stays in the cache and
+/// the active patrols table, so it gets another try on the next sync or after a restart.
+fn spawn_patrol_closer(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    discord_cache: &Arc<serenity::Cache>,
+    user_id: serenity::UserId,
+    left_at: chrono::NaiveDateTime,
+    delay: Duration,
+) {
+    let conn = conn.clone();
+    let patrol_cache = patrol_cache.clone();
+    let discord_cache = discord_cache.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let retry_delay = Duration::from_secs(CONFIG.patrol_time.write_retry_seconds);
+        for attempt in 0..=CONFIG.patrol_time.write_retries {
+            match close_pending_patrol(&conn, &patrol_cache, &discord_cache, user_id, left_at).await
+            {
+                Ok(()) => return,
+                Err(err) => println!(
+                    "Failed closing the pending patrol of {} (attempt {}): {}",
+                    user_id.0,
+                    attempt + 1,
+                    err
+                ),
+            }
+            tokio::time::sleep(retry_delay).await;
+        }
+        println!("Giving up on closing the patrol of {} until the next sync", user_id.0);
+    });
+}
+
+/// Close the patrol of an officer if they are still gone since leaving comms at the time given.
+async fn close_pending_patrol(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    discord_cache: &Arc<serenity::Cache>,
+    user_id: serenity::UserId,
+    left_at: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    // Only close the patrol if the officer hasn't come back since, copying it so the cache isn't
+    // locked while saving
+    let patrol_log = match patrol_cache.read().await.get(&user_id.0) {
+        Some(patrol_log) => patrol_log.clone(),
+        None => return Ok(()),
+    };
+    let last_log =
+        patrol_log.voice_log.last().ok_or_else(|| Error::from(no_voice_log_err(user_id)))?;
+    if last_log.end != Some(left_at) {
+        return Ok(());
+    }
+
+    // Write the results to the database and remove the patrol from the cache
+    println!("{} didn't rejoin within the grace period, going off duty", user_id.0);
+    save_patrol(conn, discord_cache, &patrol_log, left_at).await?;
+    let removed = patrol_cache.write().await.remove(&user_id.0);
+
+    // An officer that rejoined while the patrol was being saved starts a new one
+    let rejoined_log = removed
+        .and_then(|patrol_log| patrol_log.voice_log.last().cloned())
+        .filter(|last_log| last_log.end.is_none());
+    if let Some(last_log) = rejoined_log {
+        println!("{} rejoined while their patrol was being closed, going on duty", user_id.0);
+        go_on_duty(
+            conn,
+            patrol_cache,
+            user_id,
+            last_log.guild_id,
+            last_log.channel_id,
+            last_log.voice_status(),
+        )
+        .await?;
     }
 
     Ok(())
 }
 
+/// Close the patrol of every officer in the cache, ending them at the time given.
+///
+/// This is used when the bot shuts down, failures are only logged so one officer can't stop the
+/// patrols of the others from being saved. Patrols that failed stay in the active patrols table
+/// and are closed after the next start.
+pub async fn close_all_patrols(
+    conn: &DatabaseConnection,
+    patrol_cache: &PatrolCache,
+    discord_cache: &Arc<serenity::Cache>,
+    end: chrono::NaiveDateTime,
+) {
+    let cached_officers: Vec<_> =
+        patrol_cache.read().await.values().map(|patrol_log| patrol_log.officer_id).collect();
+    for user_id in cached_officers {
+        println!("Closing the patrol of {} for shutdown", user_id.0);
+        if let Err(err) = go_off_duty(conn, patrol_cache, discord_cache, user_id, end).await {
+            println!("Failed closing the patrol of {} on shutdown: {}", user_id.0, err);
+        }
+    }
+}
+
 /// Register a user switching on duty comms
+///
+/// This is also used for officers rejoining while their patrol is pending close, rejoining the
+/// channel they left from continues the voice log they had there. Staying in the same channel
+/// only updates whether the officer is muted or deafened.
 async fn move_on_duty_vc(
+    conn: &DatabaseConnection,
     patrol_cache: &PatrolCache,
     user_id: serenity::UserId,
     guild_id: serenity::GuildId,
     channel_id: serenity::ChannelId,
+    status: VoiceStatus,
 ) -> Result<(), Error> {
-    // Get a write lock to the cache
-    let mut patrol_cache_lock = patrol_cache.write().await;
-    let patrol_cache_map = &mut *patrol_cache_lock;
-
-    // Get the patrol log for specified officer
+    // Copy the last VC time so the cache isn't locked during the database writes
     let now = chrono::Utc::now().naive_utc();
-    let patrol_log = patrol_cache_map.get_mut(&user_id.0).ok_or(format!(
-        "Officer not on duty ({}) but tried to move from one on duty VC to another one.",
-        user_id
-    ))?;
+    let last_log = patrol_cache
+        .read()
+        .await
+        .get(&user_id.0)
+        .ok_or(format!(
+            "Officer not on duty ({}) but tried to move from one on duty VC to another one.",
+            user_id
+        ))?
+        .voice_log
+        .last()
+        .cloned()
+        .ok_or_else(|| Error::from(no_voice_log_err(user_id)))?;
 
-    // End the last VC time
-    patrol_log.voice_log.last_mut().ok_or_else(|| Error::from(no_voice_log_err(user_id)))?.end =
-        Some(now);
+    // Staying in or coming back to the same channel continues the last VC time
+    if last_log.channel_id == channel_id {
+        if last_log.end.is_some() {
+            reopen_active_channel_log(conn, user_id, &last_log).await?;
+        }
+        if last_log.voice_status() != status {
+            save_active_voice_status(conn, user_id, status, now).await?;
+        }
+
+        if let Some(last_log) = patrol_cache
+            .write()
+            .await
+            .get_mut(&user_id.0)
+            .and_then(|patrol_log| patrol_log.voice_log.last_mut())
+        {
+            last_log.end = None;
+            last_log.set_voice_status(status, now);
+        }
+        return Ok(());
+    }
+
+    let mut channel_log =
+        ChannelLog { guild_id, channel_id, start: now, end: None, mute_log: Vec::new() };
+    channel_log.set_voice_status(status, now);
+
+    // Write the switch through to the database first so the cache only changes if that worked
+    end_active_channel_log(conn, user_id, now).await?;
+    save_active_channel_log(conn, user_id, &channel_log).await?;
+    save_active_voice_status(conn, user_id, status, now).await?;
+
+    // Get a write lock to the cache now the database is up to date
+    let mut patrol_cache_lock = patrol_cache.write().await;
+    let patrol_log = patrol_cache_lock
+        .get_mut(&user_id.0)
+        .ok_or(format!("Officer ({}) went off duty while moving on duty VC.", user_id))?;
+
+    // End the last VC time if the officer didn't already leave it
+    if let Some(last_log) = patrol_log.voice_log.last_mut() {
+        last_log.set_voice_status(VoiceStatus::Active, now);
+        last_log.end = last_log.end.or(Some(now));
+    }
 
     // Start the new VC time
-    patrol_log.voice_log.push(ChannelLog { guild_id, channel_id, start: now, end: None });
+    patrol_log.voice_log.push(channel_log);
 
     Ok(())
 }
@@ -380,17 +1249,120 @@ fn get_channel_name(ctx: &serenity::Context, channel_id: serenity::ChannelId) ->
         .unwrap_or_else(|| "Unknown".to_owned())
 }
 
+/// Bring the patrol cache in line with the voice states of the guild.
+///
+/// Voice state updates that happen while the bot is disconnected are never received, so this is
+/// run whenever the bot (re)connects. Officers sitting in monitored channels are put on duty,
+/// officers that switched channels are moved and officers that left have their patrol closed.
+async fn sync_voice_states(
+    conn: &DatabaseConnection,
+    ctx: &serenity::Context,
+    patrol_cache: &PatrolCache,
+    voice_states: &HashMap<serenity::UserId, serenity::VoiceState>,
+) -> Result<(), Error> {
+    let guild_id = serenity::GuildId(CONFIG.guild_id);
+
+    // Find everyone that is in a monitored channel at the moment
+    let mut monitored_states = HashMap::new();
+    for (user_id, voice_state) in voice_states {
+        if let Some(channel_id) = voice_state.channel_id {
+            if is_monitored_cat(ctx, channel_id).await? {
+                monitored_states.insert(*user_id, (channel_id, VoiceStatus::from(voice_state)));
+            }
+        }
+    }
+
+    // Close the patrols of everyone that left while the bot was disconnected, patrols recovered
+    // after a restart are closed at the last time the bot knew they were still going
+    let now = chrono::Utc::now().naive_utc();
+    let cached_officers: Vec<_> = patrol_cache
+        .read()
+        .await
+        .values()
+        .map(|patrol_log| (patrol_log.officer_id, patrol_log.recovered_until))
+        .collect();
+    for (user_id, recovered_until) in cached_officers {
+        if !monitored_states.contains_key(&user_id) {
+            println!("{} is no longer in a monitored channel, going off duty", user_id.0);
+            let end = recovered_until.unwrap_or(now);
+            match mark_left_on_duty_vc(conn, patrol_cache, user_id, end).await {
+                Ok(left_at) => spawn_patrol_closer(
+                    conn,
+                    patrol_cache,
+                    &ctx.cache,
+                    user_id,
+                    left_at,
+                    Duration::ZERO,
+                ),
+                Err(err) => println!("Failed closing the patrol of {} on sync: {}", user_id.0, err),
+            }
+        }
+    }
+
+    // Put everyone else on duty or move them to the channel they are in now
+    for (user_id, (channel_id, status)) in monitored_states {
+        let result = match get_patrol_channel(patrol_cache, user_id).await? {
+            None => {
+                println!(
+                    "{} is in {} ({}) on sync, going on duty",
+                    user_id.0,
+                    get_channel_name(ctx, channel_id),
+                    channel_id.0,
+                );
+                go_on_duty(conn, patrol_cache, user_id, guild_id, channel_id, status).await
+            }
+            Some(current_channel_id) if current_channel_id != channel_id => {
+                println!(
+                    "{} moved to {} ({}) while the bot was disconnected",
+                    user_id.0,
+                    get_channel_name(ctx, channel_id),
+                    channel_id.0,
+                );
+                move_on_duty_vc(conn, patrol_cache, user_id, guild_id, channel_id, status).await
+            }
+            // Continues the patrol if it was pending close and updates the voice status
+            Some(_) => {
+                move_on_duty_vc(conn, patrol_cache, user_id, guild_id, channel_id, status).await
+            }
+        };
+        if let Err(err) = result {
+            println!("Failed syncing the patrol of {}: {}", user_id.0, err);
+        }
+    }
+
+    // Everyone left in the cache has been confirmed to still be on patrol
+    for patrol_log in patrol_cache.write().await.values_mut() {
+        patrol_log.recovered_until = None;
+    }
+
+    Ok(())
+}
+
 pub async fn event_listener(
     ctx: &serenity::Context,
     event: &poise::Event<'_>,
     _framework: &poise::Framework<Data, Error>,
     user_data: &Data,
 ) -> Result<(), Error> {
+    let conn = &user_data.db;
     match event {
         poise::Event::Ready { data_about_bot: _ } => {
-            // TODO: Add people that are on patrol when the bot starts
+            // The voice states of the guild aren't known until its GuildCreate event arrives right
+            // after this, officers that are already on patrol get added then.
             println!("Patrol Measurement Ready!")
         }
+        poise::Event::GuildCreate { guild, is_new: _ } if guild.id.0 == CONFIG.guild_id => {
+            println!("Syncing patrols with the voice states of {}", guild.name);
+            sync_voice_states(conn, ctx, &user_data.patrol_cache, &guild.voice_states).await?;
+        }
+        poise::Event::Resume { event: _ } => {
+            let voice_states = ctx
+                .cache
+                .guild_field(CONFIG.guild_id, |guild| guild.voice_states.clone())
+                .ok_or_else(|| CONFIG.guild_error_text.clone())?;
+            println!("Syncing patrols with the voice states after resuming");
+            sync_voice_states(conn, ctx, &user_data.patrol_cache, &voice_states).await?;
+        }
         poise::Event::VoiceStateUpdate { old: _, new } => match new.guild_id {
             // Measure patrol time in the main LPD server
             Some(guild_id) if guild_id.0 == CONFIG.guild_id => {
@@ -402,38 +1374,63 @@ pub async fn event_listener(
                     .unwrap_or_else(|| "Unknown".to_owned());
                 let patrol_cache = &user_data.patrol_cache;
                 let on_patrol = is_on_patrol(patrol_cache, user_id).await?;
+                let patrol_channel = get_patrol_channel(patrol_cache, user_id).await?;
+                let status = VoiceStatus::from(new);
 
                 match new.channel_id {
-                    Some(channel_id) if is_monitored_cat(ctx, channel_id).await? => match on_patrol
-                    {
-                        // An officer is going on duty
-                        false => {
-                            println!(
-                                "{}, ({}) is going on duty in {} ({})",
-                                user_name,
-                                user_id.0,
-                                get_channel_name(ctx, channel_id),
-                                channel_id.0,
-                            );
-                            go_on_duty(patrol_cache, user_id, guild_id, channel_id).await?;
-                        }
-                        // An officer is moving from voice channel to the other
-                        true => {
-                            println!(
-                                "{}, ({}) is on duty and switching to {} ({})",
-                                user_name,
-                                user_id.0,
-                                get_channel_name(ctx, channel_id),
-                                channel_id.0,
-                            );
-                            move_on_duty_vc(patrol_cache, user_id, guild_id, channel_id).await?;
+                    // An officer is muting or deafening in the channel they are already in
+                    Some(channel_id) if on_patrol && patrol_channel == Some(channel_id) => {
+                        move_on_duty_vc(conn, patrol_cache, user_id, guild_id, channel_id, status)
+                            .await?;
+                    }
+                    Some(channel_id) if is_monitored_cat(ctx, channel_id).await? => {
+                        match patrol_channel {
+                            // An officer is going on duty
+                            None => {
+                                println!(
+                                    "{}, ({}) is going on duty in {} ({})",
+                                    user_name,
+                                    user_id.0,
+                                    get_channel_name(ctx, channel_id),
+                                    channel_id.0,
+                                );
+                                go_on_duty(
+                                    conn,
+                                    patrol_cache,
+                                    user_id,
+                                    guild_id,
+                                    channel_id,
+                                    status,
+                                )
+                                .await?;
+                            }
+                            // An officer is moving from voice channel to the other or is rejoining
+                            // within the grace period
+                            Some(_) => {
+                                println!(
+                                    "{}, ({}) is on duty and switching to {} ({})",
+                                    user_name,
+                                    user_id.0,
+                                    get_channel_name(ctx, channel_id),
+                                    channel_id.0,
+                                );
+                                move_on_duty_vc(
+                                    conn,
+                                    patrol_cache,
+                                    user_id,
+                                    guild_id,
+                                    channel_id,
+                                    status,
+                                )
+                                .await?;
+                            }
                         }
-                    },
+                    }
                     // An officer is leaving on duty comms
                     Some(_) | None if on_patrol => {
-                        // Someone is going off duty
-                        println!("{}, ({}) is going off duty", user_name, user_id.0);
-                        go_off_duty(patrol_cache, &ctx.cache, user_id).await?;
+                        // Someone is going off duty once the grace period is over
+                        println!("{}, ({}) is leaving on duty comms", user_name, user_id.0);
+                        leave_on_duty_vc(conn, patrol_cache, &ctx.cache, user_id).await?;
                     }
                     _ => {}
                 }
@@ -446,8 +1443,77 @@ pub async fn event_listener(
     Ok(())
 }
 
-pub async fn cache_init() -> PatrolCache {
-    Arc::new(RwLock::new(HashMap::new()))
+/// Keep the last_seen time of the active patrols in the database up to date.
+///
+/// This runs forever, it is used to know roughly when the bot went down so patrols recovered
+/// after a crash can be closed at that time if the officer is gone by the time it is back.
+pub async fn heartbeat(conn: DatabaseConnection) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(CONFIG.patrol_time.heartbeat_seconds));
+    loop {
+        interval.tick().await;
+
+        let result = active_patrol::Entity::update_many()
+            .col_expr(active_patrol::Column::LastSeen, Expr::value(chrono::Utc::now().naive_utc()))
+            .exec(&conn)
+            .await;
+        if let Err(err) = result {
+            println!("Failed updating the heartbeat of the active patrols: {}", err);
+        }
+    }
+}
+
+pub async fn cache_init(conn: &DatabaseConnection) -> PatrolCache {
+    // Reload the patrols that were still going when the bot stopped
+    let active_logs = active_patrol::Entity::find()
+        .order_by_asc(active_patrol::Column::Start)
+        .order_by_asc(active_patrol::Column::Id)
+        .all(conn)
+        .await
+        .expect("Couldn't fetch the active patrols from the database.");
+
+    // Group the voice logs back together into patrols
+    let mut patrol_data: HashMap<u64, PatrolLog> = HashMap::new();
+    for active_log in active_logs {
+        let patrol_log = patrol_data.entry(active_log.officer_id).or_insert_with(|| PatrolLog {
+            officer_id: active_log.officer_id.into(),
+            voice_log: Vec::new(),
+            recovered_until: Some(active_log.last_seen),
+        });
+        patrol_log.recovered_until = patrol_log.recovered_until.max(Some(active_log.last_seen));
+        patrol_log.voice_log.push(ChannelLog {
+            guild_id: active_log.guild_id.into(),
+            channel_id: active_log.channel_id.into(),
+            start: active_log.start,
+            end: active_log.end,
+            mute_log: Vec::new(),
+        });
+    }
+
+    // Put the mute logs back into the voice logs they started in
+    let active_mutes = active_patrol_mute::Entity::find()
+        .order_by_asc(active_patrol_mute::Column::Start)
+        .order_by_asc(active_patrol_mute::Column::Id)
+        .all(conn)
+        .await
+        .expect("Couldn't fetch the active patrol mutes from the database.");
+    for active_mute in active_mutes {
+        let channel_log = patrol_data.get_mut(&active_mute.officer_id).and_then(|patrol_log| {
+            patrol_log.voice_log.iter_mut().rev().find(|log| log.start <= active_mute.start)
+        });
+        if let Some(channel_log) = channel_log {
+            channel_log.mute_log.push(MuteLog {
+                start: active_mute.start,
+                end: active_mute.end,
+                deafened: active_mute.deafened,
+            });
+        }
+    }
+    if !patrol_data.is_empty() {
+        println!("Recovered {} patrols that were still going on shutdown", patrol_data.len());
+    }
+
+    Arc::new(RwLock::new(patrol_data))
 }
 
 #[cfg(test)]
@@ -458,8 +1524,8 @@ mod tests {
     fn test_is_monitored_channel() {
         let monitored_channel = serenity::ChannelId::from(566802620799516672);
         let random_channel = serenity::ChannelId::from(345763573642542534);
-        assert_eq!(is_monitored(monitored_channel, None), true);
-        assert_eq!(is_monitored(random_channel, None), false);
+        assert!(is_monitored(monitored_channel, None));
+        assert!(!is_monitored(random_channel, None));
     }
 
     #[test]
@@ -467,7 +1533,257 @@ mod tests {
         let monitored_category = serenity::ChannelId::from(599764719212953610);
         let random_category = serenity::ChannelId::from(346423532524764426);
         let random_channel = serenity::ChannelId::from(345763573642542534);
-        assert_eq!(is_monitored(random_channel, Some(monitored_category)), true);
-        assert_eq!(is_monitored(random_channel, Some(random_category)), false);
+        assert!(is_monitored(random_channel, Some(monitored_category)));
+        assert!(!is_monitored(random_channel, Some(random_category)));
+    }
+
+    fn channel_log(channel_id: u64, start: i64, end: Option<i64>) -> ChannelLog {
+        let time = |seconds| chrono::NaiveDateTime::from_timestamp(seconds, 0);
+        ChannelLog {
+            guild_id: serenity::GuildId(1),
+            channel_id: serenity::ChannelId(channel_id),
+            start: time(start),
+            end: end.map(time),
+            mute_log: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_on_duty() {
+        let mut patrol_log = PatrolLog {
+            officer_id: serenity::UserId(1),
+            voice_log: vec![channel_log(1, 0, Some(600)), channel_log(2, 600, None)],
+            recovered_until: None,
+        };
+        let on_duty = patrol_log.on_duty().unwrap();
+        assert_eq!(on_duty.channel_id, serenity::ChannelId(2));
+        assert_eq!(on_duty.start, chrono::NaiveDateTime::from_timestamp(0, 0));
+        assert_eq!(on_duty.status, VoiceStatus::Active);
+
+        // Officers that left are no longer on duty while their patrol waits to be closed
+        patrol_log.voice_log[1].end = Some(chrono::NaiveDateTime::from_timestamp(1200, 0));
+        assert_eq!(patrol_log.on_duty(), None);
+    }
+
+    #[test]
+    fn test_set_voice_status() {
+        let time = |seconds| chrono::NaiveDateTime::from_timestamp(seconds, 0);
+        let mut log = channel_log(1, 0, None);
+        log.set_voice_status(VoiceStatus::Muted, time(10));
+        log.set_voice_status(VoiceStatus::Muted, time(15));
+        log.set_voice_status(VoiceStatus::Deafened, time(20));
+        log.set_voice_status(VoiceStatus::Active, time(30));
+        assert_eq!(log.voice_status(), VoiceStatus::Active);
+        assert_eq!(log.mute_log.len(), 2);
+        assert_eq!((log.mute_log[0].end, log.mute_log[0].deafened), (Some(time(20)), false));
+        assert_eq!((log.mute_log[1].end, log.mute_log[1].deafened), (Some(time(30)), true));
+    }
+
+    fn channel_names(channel_id: serenity::ChannelId) -> Option<String> {
+        match channel_id.0 {
+            1 => Some("Patrol 1".to_owned()),
+            2 => Some("Patrol 2".to_owned()),
+            3 => Some("Dispatch".to_owned()),
+            4 => Some("At Station".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_get_main_channel_most_time() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![
+            channel_log(1, 0, Some(100)),
+            channel_log(2, 100, Some(250)),
+            channel_log(1, 250, Some(300)),
+            channel_log(2, 300, None),
+        ];
+        let main_channel = get_main_channel(&voice_log, end, channel_names).unwrap();
+        assert_eq!(main_channel.0, 2);
+    }
+
+    #[test]
+    fn test_get_main_channel_skips_bad_channels() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![
+            channel_log(3, 0, Some(500)),
+            channel_log(1, 500, Some(600)),
+            channel_log(4, 600, None),
+        ];
+        let main_channel = get_main_channel(&voice_log, end, channel_names).unwrap();
+        assert_eq!(main_channel.0, 1);
+    }
+
+    #[test]
+    fn test_get_main_channel_all_bad() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![channel_log(3, 0, Some(100)), channel_log(4, 100, None)];
+        let main_channel = get_main_channel(&voice_log, end, channel_names).unwrap();
+        assert_eq!(main_channel.0, 4);
+    }
+
+    #[test]
+    fn test_get_main_channel_unknown_name() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![channel_log(5, 0, Some(600)), channel_log(1, 600, None)];
+        let main_channel = get_main_channel(&voice_log, end, channel_names).unwrap();
+        assert_eq!(main_channel.0, 5);
+    }
+
+    #[test]
+    fn test_get_main_channel_no_logs() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        assert!(get_main_channel(&[], end, channel_names).is_err());
+    }
+
+    #[test]
+    fn test_remove_short_channel_logs() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![
+            channel_log(1, 0, Some(100)),
+            channel_log(2, 100, Some(105)),
+            channel_log(3, 105, Some(200)),
+            channel_log(4, 200, None),
+        ];
+        let result = remove_short_channel_logs(&voice_log, end, 30);
+        let channels: Vec<_> = result.iter().map(|log| log.channel_id.0).collect();
+        assert_eq!(channels, vec![1, 3, 4]);
+        assert_eq!(result[2].end, None);
+    }
+
+    #[test]
+    fn test_remove_short_channel_logs_merges_same_channel() {
+        let end = chrono::NaiveDateTime::from_timestamp(1000, 0);
+        let voice_log = vec![
+            channel_log(1, 0, Some(100)),
+            channel_log(2, 100, Some(110)),
+            channel_log(1, 110, Some(300)),
+        ];
+        let result = remove_short_channel_logs(&voice_log, end, 30);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].channel_id.0, 1);
+        assert_eq!(result[0].start, voice_log[0].start);
+        assert_eq!(result[0].end, voice_log[2].end);
+    }
+
+    #[test]
+    fn test_remove_short_channel_logs_keeps_longest() {
+        let end = chrono::NaiveDateTime::from_timestamp(20, 0);
+        let voice_log = vec![channel_log(1, 0, Some(5)), channel_log(2, 5, None)];
+        let result = remove_short_channel_logs(&voice_log, end, 30);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].channel_id.0, 2);
+    }
+
+    fn date_time(month: u32, day: u32, hour: u32, min: u32, sec: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2022, month, day).and_hms(hour, min, sec)
+    }
+
+    fn patrol(start: chrono::NaiveDateTime, end: chrono::NaiveDateTime) -> patrol::Model {
+        patrol::Model {
+            id: 1,
+            officer_id: 1,
+            main_channel_id: Some(1),
+            start,
+            end,
+            event_id: None,
+            ignored: false,
+            manual_by: None,
+            reason: None,
+        }
+    }
+
+    fn patrol_voice(
+        id: i32,
+        start: chrono::NaiveDateTime,
+        end: chrono::NaiveDateTime,
+    ) -> patrol_voice::Model {
+        patrol_voice::Model { id, patrol_id: 1, channel_id: 1, start, end }
+    }
+
+    #[test]
+    fn test_sum_channel_times() {
+        let mut other_channel =
+            patrol_voice(2, date_time(5, 1, 13, 0, 0), date_time(5, 1, 13, 30, 0));
+        other_channel.channel_id = 2;
+        let patrol_voices = vec![
+            patrol_voice(1, date_time(5, 1, 12, 0, 0), date_time(5, 1, 13, 0, 0)),
+            other_channel,
+            patrol_voice(3, date_time(5, 1, 13, 30, 0), date_time(5, 1, 14, 0, 0)),
+        ];
+        let channel_times = sum_channel_times(&patrol_voices);
+        assert_eq!(channel_times.len(), 2);
+        assert_eq!(channel_times[&1], 5400);
+        assert_eq!(channel_times[&2], 1800);
+    }
+
+    #[test]
+    fn test_clip_patrol_start_of_month() {
+        // A patrol going past midnight into the first day of the month
+        let (from, to) = (date_time(3, 1, 0, 0, 0), date_time(3, 31, 23, 59, 59));
+        let patrol_voices = vec![
+            patrol_voice(1, date_time(2, 28, 23, 0, 0), date_time(2, 28, 23, 30, 0)),
+            patrol_voice(2, date_time(2, 28, 23, 30, 0), date_time(3, 1, 1, 0, 0)),
+        ];
+        let (patrol, patrol_voices) = clip_patrol(
+            (patrol(date_time(2, 28, 23, 0, 0), date_time(3, 1, 1, 0, 0)), patrol_voices),
+            from,
+            to,
+        )
+        .unwrap();
+        assert_eq!((patrol.start, patrol.end), (from, date_time(3, 1, 1, 0, 0)));
+        assert_eq!(patrol_voices.len(), 1);
+        assert_eq!((patrol_voices[0].id, patrol_voices[0].start), (2, from));
+    }
+
+    #[test]
+    fn test_clip_patrol_end_of_month() {
+        // A patrol going past midnight out of the last day of the month
+        let (from, to) = (date_time(4, 1, 0, 0, 0), date_time(4, 30, 23, 59, 59));
+        let patrol_voices =
+            vec![patrol_voice(1, date_time(4, 30, 22, 0, 0), date_time(5, 1, 2, 0, 0))];
+        let (patrol, patrol_voices) = clip_patrol(
+            (patrol(date_time(4, 30, 22, 0, 0), date_time(5, 1, 2, 0, 0)), patrol_voices),
+            from,
+            to,
+        )
+        .unwrap();
+        assert_eq!((patrol.start, patrol.end), (date_time(4, 30, 22, 0, 0), to));
+        assert_eq!(patrol_voices[0].end, to);
+    }
+
+    #[test]
+    fn test_clip_patrol_boundaries_are_inclusive() {
+        let (from, to) = (date_time(6, 1, 0, 0, 0), date_time(6, 30, 23, 59, 59));
+        let ending_on_from = patrol(date_time(5, 31, 23, 0, 0), from);
+        let starting_on_to = patrol(to, date_time(7, 1, 1, 0, 0));
+        let outside = patrol(date_time(5, 31, 22, 0, 0), date_time(5, 31, 23, 59, 59));
+        assert!(clip_patrol((ending_on_from, Vec::new()), from, to).is_some());
+        assert!(clip_patrol((starting_on_to, Vec::new()), from, to).is_some());
+        assert!(clip_patrol((outside, Vec::new()), from, to).is_none());
+    }
+
+    #[test]
+    fn test_subtract_deafened_time_reranks() {
+        let entry = |officer_id, patrol_time| LeaderboardEntry { officer_id, patrol_time };
+        let mut entries = vec![entry(1, 7200), entry(2, 5400), entry(3, 3600)];
+        let deafened = HashMap::from([(1, 3600), (3, 1800)]);
+        subtract_time(&mut entries, &deafened);
+        rank_leaderboard(&mut entries);
+        let ranking: Vec<_> =
+            entries.iter().map(|entry| (entry.officer_id, entry.patrol_time)).collect();
+        assert_eq!(ranking, vec![(2, 5400), (1, 3600), (3, 1800)]);
+    }
+
+    #[test]
+    fn test_add_host_time_reranks() {
+        let entry = |officer_id, patrol_time| LeaderboardEntry { officer_id, patrol_time };
+        let mut entries = vec![entry(1, 7200), entry(2, 5400), entry(3, 3600)];
+        let host_time = HashMap::from([(3, 5400), (2, 900), (4, 1800), (5, 0)]);
+        add_host_time(&mut entries, &host_time);
+        rank_leaderboard(&mut entries);
+        let ranking: Vec<_> =
+            entries.iter().map(|entry| (entry.officer_id, entry.patrol_time)).collect();
+        assert_eq!(ranking, vec![(3, 9000), (1, 7200), (2, 6300), (4, 1800)]);
     }
 }
@@ -0,0 +1,194 @@
+use entity::sea_orm::DatabaseConnection;
+use poise::serenity_prelude as serenity;
+
+use super::event::{self, EventDetails};
+use super::voice_channel;
+use crate::config::CONFIG;
+use crate::global::{Data, Error};
+
+/// The status Discord gives scheduled events that were cancelled.
+const STATUS_CANCELED: u64 = 4;
+/// How long events last when the scheduled event doesn't have an end time, Discord only requires
+/// one for events that don't take place in a channel.
+const DEFAULT_DURATION_HOURS: i64 = 1;
+
+/// The parts of a Discord scheduled event that are mirrored into the events table.
+///
+/// Serenity doesn't know about scheduled events yet, so they are read from the raw gateway event.
+#[derive(Debug, Clone, PartialEq)]
+struct ScheduledEvent {
+    id: u64,
+    guild_id: serenity::GuildId,
+    channel_id: Option<serenity::ChannelId>,
+    creator_id: Option<serenity::UserId>,
+    name: String,
+    description: Option<String>,
+    start: chrono::NaiveDateTime,
+    end: chrono::NaiveDateTime,
+    cancelled: bool,
+}
+
+/// Read an id, which Discord sends as a string.
+fn get_id(raw: &serenity::json::Value, key: &str) -> Option<u64> {
+    raw.get(key)?.as_str()?.parse().ok()
+}
+
+fn get_time(
+    raw: &serenity::json::Value,
+    key: &str,
+) -> Result<Option<chrono::NaiveDateTime>, Error> {
+    match raw.get(key).and_then(|value| value.as_str()) {
+        Some(time) => Ok(Some(chrono::DateTime::parse_from_rfc3339(time)?.naive_utc())),
+        None => Ok(None),
+    }
+}
+
+/// Read a scheduled event from the data of a raw gateway event.
+fn parse_scheduled_event(raw: &serenity::json::Value) -> Result<ScheduledEvent, Error> {
+    let start = get_time(raw, "scheduled_start_time")?
+        .ok_or("The scheduled event doesn't have a start time.")?;
+    let end = get_time(raw, "scheduled_end_time")?
+        .filter(|end| *end > start)
+        .unwrap_or_else(|| start + chrono::Duration::hours(DEFAULT_DURATION_HOURS));
+
+    Ok(ScheduledEvent {
+        id: get_id(raw, "id").ok_or("The scheduled event doesn't have an id.")?,
+        guild_id: serenity::GuildId(
+            get_id(raw, "guild_id").ok_or("The scheduled event doesn't have a guild id.")?,
+        ),
+        channel_id: get_id(raw, "channel_id").map(serenity::ChannelId),
+        creator_id: get_id(raw, "creator_id").map(serenity::UserId),
+        name: raw.get("name").and_then(|name| name.as_str()).unwrap_or_default().to_owned(),
+        description: raw
+            .get("description")
+            .and_then(|description| description.as_str())
+            .filter(|description| !description.is_empty())
+            .map(|description| description.to_owned()),
+        start,
+        end,
+        cancelled: raw.get("status").and_then(|status| status.as_u64()) == Some(STATUS_CANCELED),
+    })
+}
+
+/// Mirror a scheduled event that was created or changed on Discord into the events table.
+async fn save_scheduled_event(
+    conn: &DatabaseConnection,
+    discord_cache: &serenity::Cache,
+    scheduled_event: ScheduledEvent,
+) -> Result<(), Error> {
+    let channel_id = match scheduled_event.channel_id {
+        Some(channel_id) => Some(
+            voice_channel::get_saved_voice_channel(
+                conn,
+                scheduled_event.guild_id,
+                channel_id,
+                discord_cache.guild_channel(channel_id),
+            )
+            .await?
+            .id,
+        ),
+        None => None,
+    };
+    let hosts = event::filter_officers(conn, scheduled_event.creator_id.into_iter().collect());
+    let details = EventDetails {
+        name: scheduled_event.name,
+        description: scheduled_event.description,
+        start: scheduled_event.start,
+        end: scheduled_event.end,
+        channel_id,
+        hosts: hosts.await?,
+    };
+    let cancelled_at = match scheduled_event.cancelled {
+        true => Some(chrono::Utc::now().naive_utc()),
+        false => None,
+    };
+
+    event::save_discord_event(conn, scheduled_event.id, details, cancelled_at).await?;
+    Ok(())
+}
+
+pub async fn event_listener(
+    ctx: &serenity::Context,
+    event: &poise::Event<'_>,
+    _framework: &poise::Framework<Data, Error>,
+    user_data: &Data,
+) -> Result<(), Error> {
+    let conn = &user_data.db;
+    if let poise::Event::Unknown { name, raw } = event {
+        let deleted = match name.as_str() {
+            "GUILD_SCHEDULED_EVENT_CREATE" | "GUILD_SCHEDULED_EVENT_UPDATE" => false,
+            "GUILD_SCHEDULED_EVENT_DELETE" => true,
+            _ => return Ok(()),
+        };
+        let scheduled_event = parse_scheduled_event(raw)
+            .map_err(|err| format!("Failed reading a scheduled event: {}", err))?;
+        if scheduled_event.guild_id.0 != CONFIG.guild_id {
+            return Ok(());
+        }
+
+        // Deleted events are kept as cancelled as patrols may already be part of them
+        let result = match deleted {
+            true => {
+                let now = chrono::Utc::now().naive_utc();
+                event::cancel_discord_event(conn, scheduled_event.id, now).await
+            }
+            false => save_scheduled_event(conn, &ctx.cache, scheduled_event).await,
+        };
+        result.map_err(|err| format!("Failed syncing a scheduled event: {}", err))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poise::serenity::json::json;
+
+    #[test]
+    fn test_parse_scheduled_event() {
+        let raw = json!({
+            "id": "41",
+            "guild_id": "42",
+            "channel_id": "43",
+            "creator_id": "44",
+            "name": "Ride-along",
+            "description": "",
+            "scheduled_start_time": "2022-05-26T19:00:00+00:00",
+            "scheduled_end_time": null,
+            "status": 1,
+        });
+        let start = chrono::NaiveDate::from_ymd(2022, 5, 26).and_hms(19, 0, 0);
+        assert_eq!(
+            parse_scheduled_event(&raw).unwrap(),
+            ScheduledEvent {
+                id: 41,
+                guild_id: serenity::GuildId(42),
+                channel_id: Some(serenity::ChannelId(43)),
+                creator_id: Some(serenity::UserId(44)),
+                name: "Ride-along".to_owned(),
+                description: None,
+                start,
+                end: start + chrono::Duration::hours(1),
+                cancelled: false,
+            }
+        );
+
+        let raw = json!({
+            "id": "41",
+            "guild_id": "42",
+            "channel_id": null,
+            "name": "Training",
+            "scheduled_start_time": "2022-05-26T19:00:00.000000+00:00",
+            "scheduled_end_time": "2022-05-26T21:30:00.000000+00:00",
+            "status": 4,
+        });
+        let scheduled_event = parse_scheduled_event(&raw).unwrap();
+        assert_eq!(scheduled_event.channel_id, None);
+        assert_eq!(scheduled_event.creator_id, None);
+        assert_eq!(scheduled_event.end, start + chrono::Duration::minutes(150));
+        assert!(scheduled_event.cancelled);
+
+        assert!(parse_scheduled_event(&json!({ "id": "41", "guild_id": "42" })).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::QueryFilter;
+use entity::status_board;
+use poise::serenity_prelude as serenity;
+
+use super::patrol_measure::{self, OnDuty, VoiceStatus};
+use crate::config::CONFIG;
+use crate::global::{Error, PatrolCache};
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Leave some room below Discord's message limit for the lines that are cut off.
+const MAX_BOARD_LENGTH: usize = 1900;
+
+/// Make a short string of how long something has been going on, like "1h 05m".
+pub fn display_elapsed(seconds: i64) -> String {
+    format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+}
+
+fn display_on_duty(
+    on_duty: &OnDuty,
+    discord_cache: &serenity::Cache,
+    now: chrono::NaiveDateTime,
+) -> String {
+    let name = discord_cache
+        .member_field(CONFIG.guild_id, on_duty.officer_id, |m| m.display_name().into_owned())
+        .unwrap_or_else(|| format!("Unknown officer ({})", on_duty.officer_id.0));
+    let status = match on_duty.status {
+        VoiceStatus::Active => "",
+        VoiceStatus::Muted => " (muted)",
+        VoiceStatus::Deafened => " (deafened)",
+    };
+    format!(
+        "{} in <#{}> for {}{}",
+        name,
+        on_duty.channel_id.0,
+        display_elapsed(now.signed_duration_since(on_duty.start).num_seconds()),
+        status
+    )
+}
+
+/// Make the list of everyone that is on duty at the moment.
+///
+/// The list is cut off when it gets too long to fit in a single message.
+pub async fn make_on_duty_list(
+    patrol_cache: &PatrolCache,
+    discord_cache: &serenity::Cache,
+    now: chrono::NaiveDateTime,
+) -> String {
+    let on_duty = patrol_measure::get_on_duty(patrol_cache).await;
+    if on_duty.is_empty() {
+        return "Nobody is on duty at the moment.".to_owned();
+    }
+
+    let mut message = format!("On duty at the moment ({}):", on_duty.len());
+    for (index, item) in on_duty.iter().enumerate() {
+        let line = display_on_duty(item, discord_cache, now);
+        if message.len() + line.len() + 1 > MAX_BOARD_LENGTH {
+            message += &format!("\n...and {} more", on_duty.len() - index);
+            break;
+        }
+        message += &format!("\n{}", line);
+    }
+    message
+}
+
+/// Get the id of the status board message in a channel, if one was posted before.
+async fn get_board_message(
+    conn: &DatabaseConnection,
+    channel_id: serenity::ChannelId,
+) -> Result<Option<serenity::MessageId>, Error> {
+    let board = status_board::Entity::find()
+        .filter(status_board::Column::ChannelId.eq(channel_id.0))
+        .one(conn)
+        .await?;
+    Ok(board.map(|board| serenity::MessageId(board.message_id)))
+}
+
+/// Store the id of a newly posted status board message, replacing the one that was stored before.
+async fn save_board_message(
+    conn: &DatabaseConnection,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+) -> Result<(), Error> {
+    status_board::Entity::delete_many()
+        .filter(status_board::Column::ChannelId.eq(channel_id.0))
+        .exec(conn)
+        .await?;
+
+    use entity::sea_orm::entity::*;
+    let active_model = status_board::ActiveModel {
+        channel_id: Set(channel_id.0),
+        message_id: Set(message_id.0),
+        ..Default::default()
+    };
+    active_model.insert(conn).await?;
+    Ok(())
+}
+
+/// Edit the status board message, or post a new one if there isn't one or it can't be edited.
+async fn update_board(
+    conn: &DatabaseConnection,
+    http: &serenity::Http,
+    channel_id: serenity::ChannelId,
+    message_id: Option<serenity::MessageId>,
+    content: &str,
+) -> Result<serenity::MessageId, Error> {
+    if let Some(message_id) = message_id {
+        match channel_id.edit_message(http, message_id, |m| m.content(content)).await {
+            Ok(_) => return Ok(message_id),
+            // The message was most likely deleted, post a new one instead
+            Err(err) => println!("Failed editing the status board, posting a new one: {}", err),
+        }
+    }
+
+    let message = channel_id.say(http, content).await?;
+    save_board_message(conn, channel_id, message.id).await?;
+    Ok(message.id)
+}
+
+/// Keep the status board in the channel from the settings up to date forever.
+///
+/// The id of the message is stored in the database so the same message keeps being used after a
+/// restart.
+pub async fn run(
+    conn: DatabaseConnection,
+    http: Arc<serenity::Http>,
+    discord_cache: Arc<serenity::Cache>,
+    patrol_cache: PatrolCache,
+) {
+    let channel_id = serenity::ChannelId(CONFIG.status_board.channel);
+    let mut message_id = None;
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(CONFIG.status_board.update_seconds));
+    loop {
+        interval.tick().await;
+
+        if message_id.is_none() {
+            match get_board_message(&conn, channel_id).await {
+                Ok(id) => message_id = id,
+                Err(err) => {
+                    println!("Failed getting the status board message: {}", err);
+                    continue;
+                }
+            }
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        let content = make_on_duty_list(&patrol_cache, &discord_cache, now).await;
+        match update_board(&conn, &http, channel_id, message_id, &content).await {
+            Ok(id) => message_id = Some(id),
+            Err(err) => println!("Failed updating the status board: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_elapsed() {
+        assert_eq!(display_elapsed(0), "0h 00m");
+        assert_eq!(display_elapsed(3900), "1h 05m");
+        assert_eq!(display_elapsed(37 * 3600 + 59), "37h 00m");
+    }
+}
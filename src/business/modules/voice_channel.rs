@@ -0,0 +1,275 @@
+use entity::saved_voice_channel;
+use entity::saved_voice_channel_name;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::TransactionTrait;
+use migration::DbErr;
+use poise::serenity_prelude as serenity;
+
+use crate::global::{Data, Error};
+
+/// Get a saved voice channel or create one in the database if it doesn't exist.
+///
+/// The name, category and channel type are stored when the channel is created, channels saved
+/// before that was done get them filled in as soon as they are known. Pass [`None`] for the
+/// channel if it isn't known, like when it isn't in the cache.
+pub async fn get_saved_voice_channel(
+    conn: &DatabaseConnection,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    channel: Option<serenity::GuildChannel>,
+) -> Result<saved_voice_channel::Model, Error> {
+    // Try to get the channel
+    let try_get_channel_query = saved_voice_channel::Entity::find()
+        .filter(saved_voice_channel::Column::GuildId.eq(guild_id.0))
+        .filter(saved_voice_channel::Column::ChannelId.eq(channel_id.0));
+    let saved_channel = try_get_channel_query.clone().one(conn).await?;
+
+    match (saved_channel, channel) {
+        // Channels saved before their names were stored get them as soon as they are known
+        (Some(saved_channel), Some(channel)) if saved_channel.name.is_empty() => {
+            update_saved_voice_channel(conn, saved_channel, &channel).await
+        }
+        // Channel can just be returned as it existed already
+        (Some(saved_channel), _) => Ok(saved_channel),
+        (None, channel) => {
+            // The channel doesn't exist, create it instead
+            use entity::sea_orm::entity::*;
+            let active_model = saved_voice_channel::ActiveModel {
+                guild_id: Set(guild_id.0),
+                channel_id: Set(channel_id.0),
+                name: Set(channel.as_ref().map(|c| c.name.clone()).unwrap_or_default()),
+                category_id: Set(channel.as_ref().and_then(|c| c.parent_id).map(|id| id.0)),
+                channel_type: Set(channel.as_ref().map(|c| c.kind.name().to_owned())),
+                deleted_at: Set(None),
+                ..Default::default()
+            };
+
+            // Try to get the model anyway if there is a DB error as it may just mean the channel
+            // has already been added by another thread
+            match active_model.insert(conn).await {
+                Ok(model) => Ok(model),
+                Err(err) => match err {
+                    // Ignore the error if it failed inserting as it may have been because of
+                    // another thread, meaning it can still be fetched
+                    DbErr::Exec(_) => try_get_channel_query
+                        .one(conn)
+                        .await?
+                        .ok_or_else(|| format!("Failed to insert into the database: \"{}\"\nThe saved voice channel still couldn't be found.", err).into()),
+                    _ => Err(err.into()),
+                },
+            }
+        }
+    }
+}
+
+/// Bring a saved voice channel in line with the Discord channel.
+///
+/// The old name is kept in the rename history if the channel was renamed, so patrols from before
+/// can still show the name the channel had back then.
+async fn update_saved_voice_channel(
+    conn: &DatabaseConnection,
+    saved_channel: saved_voice_channel::Model,
+    channel: &serenity::GuildChannel,
+) -> Result<saved_voice_channel::Model, Error> {
+    let category_id = channel.parent_id.map(|id| id.0);
+    let channel_type = Some(channel.kind.name().to_owned());
+    if saved_channel.name == channel.name
+        && saved_channel.category_id == category_id
+        && saved_channel.channel_type == channel_type
+        && saved_channel.deleted_at.is_none()
+    {
+        return Ok(saved_channel);
+    }
+
+    use entity::sea_orm::entity::*;
+    let txn = conn.begin().await?;
+    if !saved_channel.name.is_empty() && saved_channel.name != channel.name {
+        println!("Voice channel {} was renamed to {}", saved_channel.name, channel.name);
+        let name_model = saved_voice_channel_name::ActiveModel {
+            saved_voice_channel_id: Set(saved_channel.id),
+            name: Set(saved_channel.name.clone()),
+            renamed_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        };
+        name_model.insert(&txn).await?;
+    }
+
+    let mut active_model: saved_voice_channel::ActiveModel = saved_channel.into();
+    active_model.name = Set(channel.name.clone());
+    active_model.category_id = Set(category_id);
+    active_model.channel_type = Set(channel_type);
+    active_model.deleted_at = Set(None);
+    let model = active_model.update(&txn).await?;
+    txn.commit().await?;
+
+    Ok(model)
+}
+
+/// Mark a saved voice channel as deleted on Discord, keeping everything else as it was.
+async fn mark_deleted(
+    conn: &DatabaseConnection,
+    saved_channel: saved_voice_channel::Model,
+) -> Result<(), Error> {
+    if saved_channel.deleted_at.is_some() {
+        return Ok(());
+    }
+
+    println!("Voice channel {} ({}) was deleted", saved_channel.name, saved_channel.channel_id);
+    use entity::sea_orm::entity::*;
+    let mut active_model: saved_voice_channel::ActiveModel = saved_channel.into();
+    active_model.deleted_at = Set(Some(chrono::Utc::now().naive_utc()));
+    active_model.update(conn).await?;
+    Ok(())
+}
+
+/// Pick the name a channel had at some time from its current name and rename history.
+fn name_at(
+    current_name: &str,
+    old_names: &[saved_voice_channel_name::Model],
+    time: chrono::NaiveDateTime,
+) -> String {
+    old_names
+        .iter()
+        .filter(|old_name| old_name.renamed_at > time)
+        .min_by_key(|old_name| old_name.renamed_at)
+        .map(|old_name| old_name.name.clone())
+        .unwrap_or_else(|| current_name.to_owned())
+}
+
+/// Get the names saved voice channels had at the times given, in the same order as they are given.
+pub async fn get_channel_names_at(
+    conn: &DatabaseConnection,
+    channel_times: &[(i32, chrono::NaiveDateTime)],
+) -> Result<Vec<String>, Error> {
+    let channel_ids: Vec<_> = channel_times.iter().map(|(channel_id, _)| *channel_id).collect();
+    if channel_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let channels = saved_voice_channel::Entity::find()
+        .find_with_related(saved_voice_channel_name::Entity)
+        .filter(saved_voice_channel::Column::Id.is_in(channel_ids))
+        .all(conn)
+        .await?;
+
+    Ok(channel_times
+        .iter()
+        .map(|(channel_id, time)| {
+            match channels.iter().find(|(channel, _)| channel.id == *channel_id) {
+                Some((channel, old_names)) => name_at(&channel.name, old_names, *time),
+                None => "Unknown".to_owned(),
+            }
+        })
+        .collect())
+}
+
+/// Find the saved voice channel for a Discord channel, if it was ever saved.
+async fn find_saved_voice_channel(
+    conn: &DatabaseConnection,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Result<Option<saved_voice_channel::Model>, Error> {
+    Ok(saved_voice_channel::Entity::find()
+        .filter(saved_voice_channel::Column::GuildId.eq(guild_id.0))
+        .filter(saved_voice_channel::Column::ChannelId.eq(channel_id.0))
+        .one(conn)
+        .await?)
+}
+
+/// Bring all the saved voice channels of a guild in line with its channels.
+///
+/// Channel updates that happen while the bot is disconnected are never received, so this is run
+/// whenever the guild becomes available.
+async fn sync_saved_voice_channels(
+    conn: &DatabaseConnection,
+    guild: &serenity::Guild,
+) -> Result<(), Error> {
+    let saved_channels = saved_voice_channel::Entity::find()
+        .filter(saved_voice_channel::Column::GuildId.eq(guild.id.0))
+        .all(conn)
+        .await?;
+
+    for saved_channel in saved_channels {
+        let channel_id = serenity::ChannelId(saved_channel.channel_id);
+        let result = match guild.channels.get(&channel_id) {
+            Some(serenity::Channel::Guild(channel)) => {
+                update_saved_voice_channel(conn, saved_channel, channel).await.map(|_| ())
+            }
+            Some(_) => Ok(()),
+            None => mark_deleted(conn, saved_channel).await,
+        };
+        if let Err(err) = result {
+            println!("Failed syncing saved voice channel {}: {}", channel_id.0, err);
+        }
+    }
+    Ok(())
+}
+
+pub async fn event_listener(
+    _ctx: &serenity::Context,
+    event: &poise::Event<'_>,
+    _framework: &poise::Framework<Data, Error>,
+    user_data: &Data,
+) -> Result<(), Error> {
+    let conn = &user_data.db;
+    match event {
+        poise::Event::GuildCreate { guild, is_new: _ } => {
+            sync_saved_voice_channels(conn, guild)
+                .await
+                .map_err(|err| format!("Failed syncing the saved voice channels: {}", err))?;
+        }
+        poise::Event::ChannelUpdate { old: _, new: serenity::Channel::Guild(channel) } => {
+            if let Some(saved_channel) =
+                find_saved_voice_channel(conn, channel.guild_id, channel.id).await?
+            {
+                update_saved_voice_channel(conn, saved_channel, channel)
+                    .await
+                    .map_err(|err| format!("Failed updating a saved voice channel: {}", err))?;
+            }
+        }
+        poise::Event::ChannelDelete { channel } => {
+            if let Some(saved_channel) =
+                find_saved_voice_channel(conn, channel.guild_id, channel.id).await?
+            {
+                mark_deleted(conn, saved_channel).await.map_err(|err| {
+                    format!("Failed marking a saved voice channel deleted: {}", err)
+                })?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_time(hour: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2022, 5, 1).and_hms(hour, 0, 0)
+    }
+
+    fn old_name(name: &str, hour: u32) -> saved_voice_channel_name::Model {
+        saved_voice_channel_name::Model {
+            id: 1,
+            saved_voice_channel_id: 1,
+            name: name.to_owned(),
+            renamed_at: date_time(hour),
+        }
+    }
+
+    #[test]
+    fn test_name_at() {
+        // Renamed from "Patrol" to "Training" at 12:00 and to "At Station" at 18:00
+        let old_names = vec![old_name("Training", 18), old_name("Patrol", 12)];
+        assert_eq!(name_at("At Station", &old_names, date_time(10)), "Patrol");
+        assert_eq!(name_at("At Station", &old_names, date_time(12)), "Training");
+        assert_eq!(name_at("At Station", &old_names, date_time(15)), "Training");
+        assert_eq!(name_at("At Station", &old_names, date_time(20)), "At Station");
+        assert_eq!(name_at("At Station", &[], date_time(10)), "At Station");
+    }
+}
@@ -56,3 +56,8 @@ pub async fn get_role_members(
         .collect::<Vec<_>>()
         .await
 }
+
+/// Check if a member has the leadership role.
+pub fn has_leadership_role(member: &serenity::Member) -> bool {
+    member.roles.iter().any(|role_id| role_id.0 == CONFIG.roles.leadership)
+}
@@ -0,0 +1,140 @@
+use super::helper::{add_hours, check_hours, is_leadership, send_long};
+use crate::business as bs;
+use crate::display::display_duration;
+use crate::global::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+/// Add patrol time to an officer by hand, like when the bot was down.
+///
+/// The time is added as a patrol of its own that starts at the date and time given.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Adjustments",
+    check = "is_leadership"
+)]
+pub async fn add_time(
+    ctx: Context<'_>,
+    #[description = "The officer to add the time to."] officer: serenity::User,
+    #[description = "The date the patrol was on in the format YYYY-MM-DD."] date: chrono::NaiveDate,
+    #[description = "The time the patrol started at in the format HH:MM:SS, defaults to 00:00:00."]
+    start_time: Option<chrono::NaiveTime>,
+    #[description = "How many hours to add."] hours: f64,
+    #[description = "Why the time is added."] reason: String,
+    #[description = "The number of the event the patrol was part of."] event_id: Option<i32>,
+) -> Result<(), Error> {
+    let start = date.and_time(start_time.unwrap_or_else(|| chrono::NaiveTime::from_hms(0, 0, 0)));
+    let end = add_hours(start, hours)?;
+
+    let (patrol, adjustment) = bs::adjustment::add_time(
+        &ctx.data().db,
+        officer.id,
+        start,
+        end,
+        event_id,
+        ctx.author().id,
+        reason,
+    )
+    .await?;
+    ctx.say(format!(
+        "Added patrol #{} from {} to {} to {} (adjustment #{}).",
+        patrol.id, patrol.start, patrol.end, officer, adjustment.id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Take time out of an officer's patrol time.
+///
+/// The whole patrol is taken out unless the hours to remove from it are given.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Adjustments",
+    check = "is_leadership"
+)]
+pub async fn remove_time(
+    ctx: Context<'_>,
+    #[description = "The number of the patrol, as shown when listing patrols in patrol_time."]
+    patrol_id: i32,
+    #[description = "Why the time is removed."] reason: String,
+    #[description = "How many hours to take off the patrol, defaults to the whole patrol."]
+    hours: Option<f64>,
+) -> Result<(), Error> {
+    let seconds = match hours {
+        Some(hours) => {
+            check_hours(hours)?;
+            Some((hours * 3600.0) as i64)
+        }
+        None => None,
+    };
+    let adjustment =
+        bs::adjustment::remove_time(&ctx.data().db, patrol_id, seconds, ctx.author().id, reason)
+            .await?;
+    let removed = match seconds {
+        Some(seconds) => format!("Removed {} of patrol #{}", display_duration(seconds), patrol_id),
+        None => format!("Removed patrol #{}", patrol_id),
+    };
+    ctx.say(format!(
+        "{} from the time of <@{}> (adjustment #{}).",
+        removed, adjustment.officer_id, adjustment.id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Undo time that was added or removed by hand.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Adjustments",
+    check = "is_leadership"
+)]
+pub async fn undo_adjustment(
+    ctx: Context<'_>,
+    #[description = "The number of the adjustment."] adjustment_id: i32,
+    #[description = "Why the adjustment is undone."] reason: String,
+) -> Result<(), Error> {
+    let adjustment =
+        bs::adjustment::undo_adjustment(&ctx.data().db, adjustment_id, ctx.author().id, reason)
+            .await?;
+    ctx.say(format!(
+        "Undid adjustment #{} on patrol #{} of <@{}> (adjustment #{}).",
+        adjustment_id, adjustment.patrol_id, adjustment.officer_id, adjustment.id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// List the changes that were made to the patrol time of an officer by hand.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Adjustments",
+    check = "is_leadership"
+)]
+pub async fn adjustments(
+    ctx: Context<'_>,
+    #[description = "The officer to list the adjustments of."] officer: serenity::User,
+) -> Result<(), Error> {
+    let adjustments = bs::adjustment::get_adjustments(&ctx.data().db, officer.id).await?;
+    let message = match adjustments.is_empty() {
+        true => format!("The patrol time of {} has never been adjusted.", officer),
+        false => adjustments.iter().fold(
+            format!("Adjustments to the patrol time of {}:", officer),
+            |acc, adjustment| {
+                format!("{}\n{}", acc, bs::adjustment::display_adjustment(adjustment))
+            },
+        ),
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use crate::business as bs;
+use crate::global::{Context, Error};
+
+/// Claim patrol time the bot missed, like when it was offline while you were patrolling.
+///
+/// Leadership reviews the claim, the time counts once they approve it. Times are in UTC, an end
+/// time before the start time is on the next day.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Claims")]
+pub async fn claim_time(
+    ctx: Context<'_>,
+    #[description = "The date the patrol started on in the format YYYY-MM-DD."]
+    date: chrono::NaiveDate,
+    #[description = "The time the patrol started at in the format HH:MM:SS."]
+    start_time: chrono::NaiveTime,
+    #[description = "The time the patrol ended at in the format HH:MM:SS."]
+    end_time: chrono::NaiveTime,
+    #[description = "Why the bot didn't count the time."] reason: String,
+) -> Result<(), Error> {
+    // Only officers can claim patrol time
+    let user_id = ctx.author().id;
+    let officer = bs::member_management::get_member_from_cache(&ctx.data().officer_cache, &user_id);
+    if !matches!(officer.await, Some(officer) if officer.deleted_at.is_none()) {
+        return Err("Only LPD officers can claim patrol time.".into());
+    }
+
+    let start = date.and_time(start_time);
+    let end = match end_time < start_time {
+        true => date.succ().and_time(end_time),
+        false => date.and_time(end_time),
+    };
+    let claim = bs::claim::file_claim(&ctx.data().db, user_id, start, end, reason).await?;
+    let claim = bs::claim::post_for_review(&ctx.data().db, &ctx.discord().http, claim).await?;
+    ctx.say(format!(
+        "Filed claim #{} for {} to {}, you will get a message once leadership has reviewed it.",
+        claim.id, claim.start, claim.end
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+use super::helper::{add_hours, check_hours, get_date_range, is_leadership, send_long};
+use crate::business as bs;
+use crate::config::CONFIG;
+use crate::global::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+/// Get the saved voice channel for the channel an event takes place in.
+async fn get_event_channel(ctx: Context<'_>, channel: serenity::Channel) -> Result<i32, Error> {
+    let channel = channel.guild().ok_or("Events can only take place in a server channel.")?;
+    if !matches!(channel.kind, serenity::ChannelType::Voice | serenity::ChannelType::Stage) {
+        return Err("Events can only take place in a voice channel.".into());
+    }
+    let saved_channel = bs::voice_channel::get_saved_voice_channel(
+        &ctx.data().db,
+        CONFIG.guild_id.into(),
+        channel.id,
+        Some(channel),
+    )
+    .await?;
+    Ok(saved_channel.id)
+}
+
+/// Schedule a new event.
+///
+/// Times are in UTC. Patrols in the voice channel of the event while it is going on are counted
+/// as part of it.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn event_create(
+    ctx: Context<'_>,
+    #[description = "The name of the event."] name: String,
+    #[description = "The date the event starts on in the format YYYY-MM-DD."]
+    date: chrono::NaiveDate,
+    #[description = "The time the event starts at in the format HH:MM:SS."]
+    start_time: chrono::NaiveTime,
+    #[description = "How many hours the event lasts."] hours: f64,
+    #[description = "The officer hosting the event."] host: serenity::User,
+    #[description = "An officer hosting the event together with the host."] co_host: Option<
+        serenity::User,
+    >,
+    #[description = "Another officer hosting the event together with the host."]
+    second_co_host: Option<serenity::User>,
+    #[description = "The voice channel the event takes place in."] channel: Option<
+        serenity::Channel,
+    >,
+    #[description = "What the event is about."] description: Option<String>,
+) -> Result<(), Error> {
+    let start = date.and_time(start_time);
+    let channel_id = match channel {
+        Some(channel) => Some(get_event_channel(ctx, channel).await?),
+        None => None,
+    };
+    let hosts = [Some(host), co_host, second_co_host].into_iter().flatten().map(|user| user.id);
+
+    let details = bs::event::EventDetails {
+        name,
+        description,
+        start,
+        end: add_hours(start, hours)?,
+        channel_id,
+        hosts: hosts.collect(),
+    };
+    let event = bs::event::create_event(&ctx.data().db, details).await?;
+    let info = bs::event::get_event(&ctx.data().db, event.id).await?;
+    ctx.say(format!("Created event {}", bs::event::display_event(&info))).await?;
+
+    Ok(())
+}
+
+/// Change an event, everything that isn't given is left as it was.
+///
+/// Giving a host replaces all the hosts of the event. Changing the date or start time keeps the
+/// length of the event the same unless the hours are given as well. Editing a single event of a
+/// recurring event leaves the rest of them as they are. The channel and description can be removed
+/// with event_clear.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn event_edit(
+    ctx: Context<'_>,
+    #[description = "The number of the event."] event_id: i32,
+    #[description = "The new name of the event."] name: Option<String>,
+    #[description = "The new date the event starts on in the format YYYY-MM-DD."] date: Option<
+        chrono::NaiveDate,
+    >,
+    #[description = "The new time the event starts at in the format HH:MM:SS."] start_time: Option<
+        chrono::NaiveTime,
+    >,
+    #[description = "How many hours the event lasts."] hours: Option<f64>,
+    #[description = "The officer hosting the event."] host: Option<serenity::User>,
+    #[description = "An officer hosting the event together with the host."] co_host: Option<
+        serenity::User,
+    >,
+    #[description = "Another officer hosting the event together with the host."]
+    second_co_host: Option<serenity::User>,
+    #[description = "The voice channel the event takes place in."] channel: Option<
+        serenity::Channel,
+    >,
+    #[description = "What the event is about."] description: Option<String>,
+) -> Result<(), Error> {
+    let event = bs::event::get_event(&ctx.data().db, event_id).await?.event;
+
+    // Work out the new times from the parts that are given
+    let start = date
+        .unwrap_or_else(|| event.start.date())
+        .and_time(start_time.unwrap_or_else(|| event.start.time()));
+    let end = match hours {
+        Some(hours) => add_hours(start, hours)?,
+        None => start + event.end.signed_duration_since(event.start),
+    };
+
+    let hosts = match host {
+        Some(host) => Some(
+            [Some(host), co_host, second_co_host]
+                .into_iter()
+                .flatten()
+                .map(|user| user.id)
+                .collect(),
+        ),
+        None if co_host.is_some() || second_co_host.is_some() => {
+            return Err("The co-hosts can only be changed together with the host.".into());
+        }
+        None => None,
+    };
+    let channel_id = match channel {
+        Some(channel) => Some(Some(get_event_channel(ctx, channel).await?)),
+        None => None,
+    };
+
+    let changes = bs::event::EventChanges {
+        name,
+        description: description.map(Some),
+        start: Some(start),
+        end: Some(end),
+        channel_id,
+        hosts,
+    };
+    bs::event::edit_event(&ctx.data().db, event_id, changes).await?;
+    let info = bs::event::get_event(&ctx.data().db, event_id).await?;
+    ctx.say(format!("Changed event {}", bs::event::display_event(&info))).await?;
+
+    Ok(())
+}
+
+/// The optional parts of an event that can be removed from it.
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum EventField {
+    #[name = "Channel"]
+    Channel,
+    #[name = "Description"]
+    Description,
+}
+
+/// Remove the voice channel or the description from an event.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+pub async fn event_clear(
+    ctx: Context<'_>,
+    #[description = "The number of the event."] event_id: i32,
+    #[description = "The part of the event to remove."] field: EventField,
+) -> Result<(), Error> {
+    let changes = match field {
+        EventField::Channel => {
+            bs::event::EventChanges { channel_id: Some(None), ..Default::default() }
+        }
+        EventField::Description => {
+            bs::event::EventChanges { description: Some(None), ..Default::default() }
+        }
+    };
+    bs::event::edit_event(&ctx.data().db, event_id, changes).await?;
+    let info = bs::event::get_event(&ctx.data().db, event_id).await?;
+    ctx.say(format!("Changed event {}", bs::event::display_event(&info))).await?;
+
+    Ok(())
+}
+
+/// Cancel an event that hasn't ended yet.
+///
+/// Cancelling a single event of a recurring event skips just that one, the rest of them still take
+/// place.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+pub async fn event_cancel(
+    ctx: Context<'_>,
+    #[description = "The number of the event."] event_id: i32,
+) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let event = bs::event::cancel_event(&ctx.data().db, event_id, now).await?;
+    ctx.say(format!("Cancelled event #{} {}.", event.id, event.name)).await?;
+
+    Ok(())
+}
+
+/// List the events that are coming up or going on at the moment.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Events")]
+pub async fn event_list(ctx: Context<'_>) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let events = bs::event::get_upcoming_events(&ctx.data().db, now).await?;
+    let message = match events.is_empty() {
+        true => "There are no upcoming events.".to_owned(),
+        false => events.iter().fold("Upcoming events:".to_owned(), |acc, info| {
+            format!("{}\n{}", acc, bs::event::display_event(info))
+        }),
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
+
+/// Show who was in the voice channel of an event while it was going on.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Events")]
+pub async fn event_attendance(
+    ctx: Context<'_>,
+    #[description = "The number of the event."] event_id: i32,
+) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let (info, attendance) =
+        bs::event::get_attendance(&ctx.data().db, &ctx.data().patrol_cache, event_id, now).await?;
+
+    let cache = &ctx.discord().cache;
+    let title = format!("Attendance of event #{} {}", info.event.id, info.event.name);
+    let message = match attendance.is_empty() {
+        true => format!("{}:\nNobody attended.", title),
+        false => {
+            attendance.iter().fold(format!("{} ({}):", title, attendance.len()), |acc, item| {
+                let name = cache
+                    .member_field(CONFIG.guild_id, item.officer_id, |m| {
+                        m.display_name().into_owned()
+                    })
+                    .unwrap_or_else(|| format!("<@{}>", item.officer_id.0));
+                format!("{}\n{}", acc, bs::event::display_attendance(item, &name))
+            })
+        }
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
+
+/// Show how many events each officer hosted and for how many hours.
+///
+/// Events are counted in the time period they start in, cancelled events are left out.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Events")]
+pub async fn host_stats(
+    ctx: Context<'_>,
+    #[description = "The number of days to look back for events, this defaults to 28."]
+    days: Option<i64>,
+    #[description = "From date in the format YYYY-MM-DD. This default to the same value as days."]
+    from_date: Option<chrono::NaiveDate>,
+    #[description = "To date in the format YYYY-MM-DD. This is set to the current date if it isn't given."]
+    to_date: Option<chrono::NaiveDate>,
+) -> Result<(), Error> {
+    let (from_date, to_date) = get_date_range(days, from_date, to_date)?;
+    let stats = bs::event::get_host_stats(
+        &ctx.data().db,
+        from_date.and_hms(0, 0, 0),
+        to_date.and_hms(23, 59, 59),
+    )
+    .await?;
+
+    let cache = &ctx.discord().cache;
+    let title = format!("Events hosted from {} to {}", from_date, to_date);
+    let message = match stats.is_empty() {
+        true => format!("{}:\nNo events were hosted.", title),
+        false => stats.iter().fold(format!("{}:", title), |acc, item| {
+            let name = cache
+                .member_field(CONFIG.guild_id, item.officer_id, |m| m.display_name().into_owned())
+                .unwrap_or_else(|| format!("<@{}>", item.officer_id.0));
+            format!(
+                "{}\n{} - {} events - {:.1} hours",
+                acc,
+                name,
+                item.events,
+                item.seconds as f64 / 3600.0
+            )
+        }),
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
+
+/// Set up an event that repeats, the events for the coming days are created ahead of time.
+///
+/// The recurrence is written like "weekly mon,thu", "every 3 days", "monthly 2 tue" or
+/// "monthly last fri". Times are in UTC.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn recurring_event_create(
+    ctx: Context<'_>,
+    #[description = "The name of the events."] name: String,
+    #[description = "When the events repeat, like \"weekly mon,thu\" or \"monthly last fri\"."]
+    recurrence: String,
+    #[description = "The first day the events can take place on in the format YYYY-MM-DD."]
+    first_date: chrono::NaiveDate,
+    #[description = "The time the events start at in the format HH:MM:SS."]
+    start_time: chrono::NaiveTime,
+    #[description = "How many hours the events last."] hours: f64,
+    #[description = "The officer hosting the events."] host: serenity::User,
+    #[description = "An officer hosting the events together with the host."] co_host: Option<
+        serenity::User,
+    >,
+    #[description = "Another officer hosting the events together with the host."]
+    second_co_host: Option<serenity::User>,
+    #[description = "The voice channel the events take place in."] channel: Option<
+        serenity::Channel,
+    >,
+    #[description = "What the events are about."] description: Option<String>,
+) -> Result<(), Error> {
+    check_hours(hours)?;
+    let channel_id = match channel {
+        Some(channel) => Some(get_event_channel(ctx, channel).await?),
+        None => None,
+    };
+    let hosts = [Some(host), co_host, second_co_host].into_iter().flatten().map(|user| user.id);
+
+    let details = bs::event_template::TemplateDetails {
+        name,
+        description,
+        recurrence: recurrence.parse()?,
+        first_date,
+        start_time,
+        duration_minutes: (hours * 60.0).round() as i32,
+        channel_id,
+        hosts: hosts.collect(),
+    };
+    let today = chrono::Utc::now().naive_utc().date();
+    let template = bs::event_template::create_template(&ctx.data().db, details, today).await?;
+    ctx.say(format!(
+        "Created recurring event #{} {} ({}), the events up to {} are scheduled.",
+        template.id,
+        template.name,
+        template.recurrence,
+        template.created_until.map(|date| date.to_string()).unwrap_or_default()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// List the recurring events that haven't ended.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Events")]
+pub async fn recurring_event_list(ctx: Context<'_>) -> Result<(), Error> {
+    let templates = bs::event_template::get_templates(&ctx.data().db).await?;
+    let message = match templates.is_empty() {
+        true => "There are no recurring events.".to_owned(),
+        false => templates.iter().fold("Recurring events:".to_owned(), |acc, (template, hosts)| {
+            format!("{}\n{}", acc, bs::event_template::display_template(template, hosts))
+        }),
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
+
+/// Stop a recurring event, its events that haven't started yet are cancelled.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Events",
+    check = "is_leadership"
+)]
+pub async fn recurring_event_end(
+    ctx: Context<'_>,
+    #[description = "The number of the recurring event."] recurring_event_id: i32,
+) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let template =
+        bs::event_template::end_template(&ctx.data().db, recurring_event_id, now).await?;
+    ctx.say(format!("Ended recurring event #{} {}.", template.id, template.name)).await?;
+
+    Ok(())
+}
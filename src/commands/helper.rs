@@ -1,6 +1,159 @@
+use crate::business as bs;
+use crate::business::activity_report::date_from_days;
+use crate::display::split_message;
 use crate::global::{Context, Error};
+use poise::serenity_prelude as serenity;
+use std::time::Duration;
+
+/// The most hours a command takes for a single span of time, like added time or an event.
+const MAX_HOURS: f64 = 168.0;
+
+/// Check that a number of hours given to a command is more than 0 and at most [`MAX_HOURS`].
+pub fn check_hours(hours: f64) -> Result<(), Error> {
+    // NaN fails every comparison, so it is caught by checking for the valid range
+    if !(hours > 0.0 && hours <= MAX_HOURS) {
+        return Err(format!(
+            "The number of hours has to be more than 0 and at most {}.",
+            MAX_HOURS
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Get the time the number of hours given after the start.
+pub fn add_hours(start: chrono::NaiveDateTime, hours: f64) -> Result<chrono::NaiveDateTime, Error> {
+    check_hours(hours)?;
+    let duration = chrono::Duration::seconds((hours * 3600.0) as i64);
+    Ok(start.checked_add_signed(duration).ok_or("The end time is out of range.")?)
+}
+
+/// Get the dates a command covers from either the number of days to look back or the from date,
+/// looking back 28 days if neither is given. The to date defaults to the current date.
+pub fn get_date_range(
+    days: Option<i64>,
+    from_date: Option<chrono::NaiveDate>,
+    to_date: Option<chrono::NaiveDate>,
+) -> Result<(chrono::NaiveDate, chrono::NaiveDate), Error> {
+    let to_date = to_date.unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
+    let from_date = match (days, from_date) {
+        (Some(_), Some(_)) => {
+            return Err("days and from_date can't both be provided at the same time.".into());
+        }
+        (None, Some(from_date)) => from_date,
+        (Some(days), None) => date_from_days(days)?,
+        (None, None) => date_from_days(28)?,
+    };
+    Ok((from_date, to_date))
+}
+
 pub async fn send_long(ctx: Context<'_>, message: &str) -> Result<(), Error> {
-    // TODO: Actually allow messages over 2000 characters
-    ctx.say(message).await?;
+    // Code blocks that get split up aren't closed and opened again in the next message
+    for part in split_message(message) {
+        ctx.say(part).await?;
+    }
+    Ok(())
+}
+
+/// Check if the member that used the command has the leadership role.
+pub async fn has_leadership_role(ctx: Context<'_>) -> bool {
+    match ctx.author_member().await {
+        Some(member) => bs::has_leadership_role(&member),
+        None => false,
+    }
+}
+
+/// Command check that only lets members with the leadership role through.
+pub async fn is_leadership(ctx: Context<'_>) -> Result<bool, Error> {
+    let has_role = has_leadership_role(ctx).await;
+    if !has_role {
+        ctx.say("Only leadership can use this command.").await?;
+    }
+    Ok(has_role)
+}
+
+/// Add the previous and next buttons for a paginated message.
+fn create_page_buttons<'a>(
+    components: &'a mut serenity::CreateComponents,
+    id_prefix: &str,
+    page: usize,
+    page_count: usize,
+) -> &'a mut serenity::CreateComponents {
+    components.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(format!("{}-prev", id_prefix)).label("Previous").disabled(page == 0)
+        })
+        .create_button(|b| {
+            b.custom_id(format!("{}-next", id_prefix))
+                .label("Next")
+                .disabled(page + 1 >= page_count)
+        })
+    })
+}
+
+/// Send a message that is split up into pages, with buttons to go through them.
+///
+/// Only the person that used the command can change the page, the buttons are removed once nobody
+/// has used them for a few minutes.
+pub async fn send_paginated(ctx: Context<'_>, pages: &[String]) -> Result<(), Error> {
+    let page_count = pages.len();
+    if page_count <= 1 {
+        return send_long(ctx, pages.first().map(String::as_str).unwrap_or_default()).await;
+    }
+
+    // Send the first page
+    let id_prefix = ctx.id().to_string();
+    let page_text = |page: usize| format!("{}\n\nPage {}/{}", pages[page], page + 1, page_count);
+    let mut page = 0;
+    let handle = ctx
+        .send(|m| {
+            m.content(page_text(page))
+                .components(|c| create_page_buttons(c, &id_prefix, page, page_count))
+        })
+        .await?;
+    let mut message = handle.message().await?;
+
+    // Change the page whenever one of the buttons is pressed
+    while let Some(interaction) = serenity::CollectComponentInteraction::new(ctx.discord())
+        .message_id(message.id)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(180))
+        .await
+    {
+        match interaction.data.custom_id.strip_prefix(&id_prefix) {
+            Some("-prev") => page = page.saturating_sub(1),
+            Some("-next") => page = (page + 1).min(page_count - 1),
+            _ => continue,
+        }
+        interaction
+            .create_interaction_response(ctx.discord(), |r| {
+                r.kind(serenity::InteractionResponseType::UpdateMessage).interaction_response_data(
+                    |d| {
+                        d.content(page_text(page))
+                            .components(|c| create_page_buttons(c, &id_prefix, page, page_count))
+                    },
+                )
+            })
+            .await?;
+    }
+
+    // Nobody is using the buttons anymore
+    message.edit(ctx.discord(), |m| m.components(|c| c)).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_hours() {
+        let start = chrono::NaiveDate::from_ymd(2022, 5, 1).and_hms(12, 0, 0);
+        assert_eq!(add_hours(start, 1.5).unwrap(), start + chrono::Duration::minutes(90));
+        assert_eq!(add_hours(start, MAX_HOURS).unwrap(), start + chrono::Duration::weeks(1));
+        for hours in [0.0, -1.0, 1e15, f64::INFINITY, f64::NAN] {
+            assert!(add_hours(start, hours).is_err());
+        }
+        assert!(add_hours(chrono::naive::MAX_DATE.and_hms(23, 0, 0), 2.0).is_err());
+    }
+}
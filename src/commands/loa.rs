@@ -0,0 +1,99 @@
+use super::helper::{has_leadership_role, is_leadership, send_long};
+use crate::business as bs;
+use crate::global::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+/// Request a leave of absence, it counts once leadership has approved it.
+#[poise::command(prefix_command, slash_command, track_edits, category = "LOA")]
+pub async fn loa_request(
+    ctx: Context<'_>,
+    #[description = "First day of the leave in the format YYYY-MM-DD."]
+    start_date: chrono::NaiveDate,
+    #[description = "Last day of the leave in the format YYYY-MM-DD."] end_date: chrono::NaiveDate,
+    #[description = "Why you are going on leave."] reason: String,
+) -> Result<(), Error> {
+    // Only officers can go on leave
+    let user_id = ctx.author().id;
+    let officer = bs::member_management::get_member_from_cache(&ctx.data().officer_cache, &user_id);
+    if !matches!(officer.await, Some(officer) if officer.deleted_at.is_none()) {
+        return Err("Only LPD officers can request a leave of absence.".into());
+    }
+
+    let loa = bs::loa::request_loa(&ctx.data().db, user_id, start_date, end_date, reason).await?;
+    ctx.say(format!(
+        "Requested leave of absence #{} from {} to {}, it counts once leadership approves it.",
+        loa.id, loa.start, loa.end
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Approve a leave of absence that was requested.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "LOA",
+    check = "is_leadership"
+)]
+pub async fn loa_approve(
+    ctx: Context<'_>,
+    #[description = "The number of the leave of absence."] loa_id: i32,
+) -> Result<(), Error> {
+    let loa = bs::loa::approve_loa(&ctx.data().db, loa_id, ctx.author().id).await?;
+    ctx.say(format!(
+        "Approved the leave of absence of <@{}>: {}",
+        loa.officer_id,
+        bs::loa::display_loa(&loa)
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// List the leaves of absence that haven't ended yet.
+#[poise::command(prefix_command, slash_command, track_edits, category = "LOA")]
+pub async fn loa_list(
+    ctx: Context<'_>,
+    #[description = "Only list the leaves of this officer."] officer: Option<serenity::User>,
+) -> Result<(), Error> {
+    let today = chrono::Utc::now().naive_utc().date();
+    let user_id = officer.map(|officer| officer.id);
+    let loas = bs::loa::get_current_loas(&ctx.data().db, user_id, today).await?;
+
+    let message = match loas.is_empty() {
+        true => "There are no current or upcoming leaves of absence.".to_owned(),
+        false => {
+            loas.iter().fold("Current and upcoming leaves of absence:".to_owned(), |acc, loa| {
+                format!("{}\n<@{}> {}", acc, loa.officer_id, bs::loa::display_loa(loa))
+            })
+        }
+    };
+    send_long(ctx, &message).await?;
+
+    Ok(())
+}
+
+/// End a leave of absence today, leaves that haven't started yet are cancelled.
+#[poise::command(prefix_command, slash_command, track_edits, category = "LOA")]
+pub async fn loa_end(
+    ctx: Context<'_>,
+    #[description = "The number of the leave of absence."] loa_id: i32,
+) -> Result<(), Error> {
+    // Officers can end their own leave, leadership can end anyone's
+    let conn = &ctx.data().db;
+    let loa = bs::loa::get_loa(conn, loa_id).await?;
+    if loa.officer_id != ctx.author().id.0 && !has_leadership_role(ctx).await {
+        return Err("Only leadership can end the leave of absence of someone else.".into());
+    }
+
+    let today = chrono::Utc::now().naive_utc().date();
+    let message = match bs::loa::end_loa(conn, loa_id, today).await? {
+        Some(loa) => format!("Ended the leave of absence: {}", bs::loa::display_loa(&loa)),
+        None => format!("Cancelled the leave of absence #{} as it hadn't started yet.", loa_id),
+    };
+    ctx.say(message).await?;
+
+    Ok(())
+}
@@ -1,5 +1,13 @@
+mod adjustment;
+mod claim;
+mod event;
 mod helper;
+mod loa;
 mod other;
 mod time;
+pub use adjustment::*;
+pub use claim::*;
+pub use event::*;
+pub use loa::*;
 pub use other::*;
 pub use time::*;
@@ -1,27 +1,11 @@
-use super::helper::send_long;
+use super::helper::{get_date_range, is_leadership, send_long, send_paginated};
 use crate::business as bs;
+use crate::business::activity_report::date_from_days;
+use crate::config::CONFIG;
+use crate::display::{display_duration, display_inactivity_report, split_duration};
 use crate::global::{Context, Error};
 use poise::serenity_prelude as serenity;
-
-fn date_from_days(days: i64) -> Result<chrono::NaiveDate, Error> {
-    Ok(chrono::Utc::now()
-        .checked_sub_signed(chrono::Duration::days(days))
-        .ok_or("From date overflow!")?
-        .naive_utc()
-        .date())
-}
-
-/// Convert seconds into weeks, days, hours, minutes and seconds
-fn split_duration(seconds: i64) -> (i64, i64, i64, i64, i64) {
-    // Calculate each duration
-    let (min_rem, seconds) = (seconds / 60, seconds % 60);
-    let (hour_rem, minutes) = (min_rem / 60, min_rem % 60);
-    let (day_rem, hours) = (hour_rem / 60, hour_rem % 60);
-    let (weeks, days) = (day_rem / 60, day_rem % 60);
-
-    // Return the values
-    (weeks, days, hours, minutes, seconds)
-}
+use std::collections::HashMap;
 
 /// Make a multi line string that represents a duration
 #[rustfmt::skip]
@@ -37,15 +21,70 @@ fn display_duration_multiline(seconds: i64) -> String {
     return_str + &format!("Seconds: {}", seconds)
 }
 
-/// Make a single line string that represents a duration
-fn display_duration(seconds: i64) -> String {
-    let (weeks, days, hours, minutes, seconds) = split_duration(seconds);
+/// Make a list of the time spent in each group, with each group's share of the total time.
+fn display_breakdown(title: &str, mut times: Vec<(String, i64)>, total: i64) -> String {
+    times.sort_by_key(|item| std::cmp::Reverse(item.1));
+    times.iter().fold(format!("{}:\n", title), |acc, (name, time)| {
+        let percentage = match total {
+            0 => 0.0,
+            _ => *time as f64 * 100.0 / total as f64,
+        };
+        format!("{}    {} - {} ({:.1}%)\n", acc, name, display_duration(*time), percentage)
+    })
+}
+
+/// Make the breakdown of an officer's patrol time per voice channel and per channel category.
+///
+/// The category names are looked up in the cache, channels in categories that no longer exist are
+/// listed under an unknown category.
+async fn channel_breakdown(
+    ctx: Context<'_>,
+    from_date: chrono::NaiveDate,
+    to_date: chrono::NaiveDate,
+    officer: &serenity::User,
+) -> Result<String, Error> {
+    let cache = &ctx.discord().cache;
+    let channel_times = bs::patrol_measure::get_channel_breakdown(
+        &ctx.data().db,
+        from_date.and_hms(0, 0, 0),
+        to_date.and_hms(23, 59, 59),
+        officer.id,
+    )
+    .await?;
+    let total = channel_times.iter().fold(0, |acc, item| acc + item.1);
 
-    // Convert the duration into a single line string
-    format!("{}:{}:{}:{}:{}", weeks, days, hours, minutes, seconds)
+    let mut by_channel = Vec::new();
+    let mut by_category: HashMap<String, i64> = HashMap::new();
+    for (channel, time) in channel_times {
+        let channel_id = serenity::ChannelId(channel.channel_id);
+        let channel_name = match channel.name.is_empty() {
+            false => channel.name,
+            true => cache
+                .guild_channel_field(channel_id, |c| c.name.clone())
+                .unwrap_or_else(|| format!("Unknown channel ({})", channel_id.0)),
+        };
+        let category_name = channel
+            .category_id
+            .and_then(|category_id| cache.category(category_id))
+            .map(|category| category.name)
+            .unwrap_or_else(|| "Unknown category".to_owned());
+
+        by_channel.push((channel_name, time));
+        *by_category.entry(category_name).or_default() += time;
+    }
+
+    Ok(format!(
+        "```\n{}{}Total: {}```",
+        display_breakdown("Time per channel", by_channel, total),
+        display_breakdown("Time per category", by_category.into_iter().collect(), total),
+        display_duration(total)
+    ))
 }
 
 /// Check patrol time of an officer.
+///
+/// The from and to dates are both included as whole days. Patrols that cross midnight at either
+/// end only count the time that falls inside of those days.
 #[poise::command(prefix_command, slash_command, track_edits, category = "Time")]
 pub async fn patrol_time(
     ctx: Context<'_>,
@@ -57,28 +96,38 @@ pub async fn patrol_time(
     to_date: Option<chrono::NaiveDate>,
     #[description = "List all the patrols in the time period specified, defaults to false."]
     list_patrols: Option<bool>,
+    #[description = "Break the time down per voice channel and category, defaults to false."]
+    breakdown: Option<bool>,
     #[description = "The officer to get the patrol time from."] officer: serenity::User,
 ) -> Result<(), Error> {
     // Setup the parameters
     let list_patrols = list_patrols.unwrap_or(false);
-    let to_date = to_date.unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
-    let from_date = match (days, from_date) {
-        (Some(_), Some(_)) => {
-            return Err("days and from_date can't both be provided at the same time.".into());
-        }
-        (None, Some(from_date)) => from_date,
-        (Some(days), None) => date_from_days(days)?,
-        (None, None) => date_from_days(28)?,
-    };
+    let (from_date, to_date) = get_date_range(days, from_date, to_date)?;
 
     let time_str = match list_patrols {
         true => {
             let patrols = bs::patrol_measure::get_patrols(
+                &ctx.data().db,
                 from_date.and_hms(0, 0, 0),
                 to_date.and_hms(23, 59, 59),
                 officer.id,
             )
             .await?;
+
+            // Show the channels with the names they had at the time of the patrol
+            let channel_times: Vec<_> = patrols
+                .iter()
+                .flat_map(|item| item.1.iter().map(|pat_vc| (pat_vc.channel_id, pat_vc.start)))
+                .collect();
+            let channel_names =
+                bs::voice_channel::get_channel_names_at(&ctx.data().db, &channel_times).await?;
+            let mut channel_names = channel_names.into_iter();
+            let cache = &ctx.discord().cache;
+
+            // Get the events the patrols were part of
+            let event_ids = patrols.iter().filter_map(|item| item.0.event_id).collect();
+            let events = bs::event::get_events(&ctx.data().db, event_ids).await?;
+
             let result = patrols.into_iter().fold(String::new(), |acc, item| {
                 // Get the duration for this patrol
                 let patrol_duration =
@@ -88,13 +137,35 @@ pub async fn patrol_time(
                 let patrol_voices = item.1.into_iter().fold(String::new(), |acc, pat_vc| {
                     let pat_dur_sec = pat_vc.end.signed_duration_since(pat_vc.start).num_seconds();
                     let pat_vc_dur = display_duration(pat_dur_sec);
-                    format!("{}    {} - {}\n", acc, pat_vc.start, pat_vc_dur)
+                    let channel_name = channel_names.next().unwrap_or_default();
+                    format!("{}    {} - {} - {}\n", acc, pat_vc.start, pat_vc_dur, channel_name)
                 });
 
+                // Patrols entered by hand don't have any voice channels, show who added them
+                let patrol_voices = match item.0.manual_by {
+                    Some(supervisor_id) => format!(
+                        "    Added by hand by {}: {}\n",
+                        cache
+                            .member_field(CONFIG.guild_id, supervisor_id, |m| m
+                                .display_name()
+                                .into_owned())
+                            .unwrap_or_else(|| supervisor_id.to_string()),
+                        item.0.reason.unwrap_or_default()
+                    ),
+                    None => patrol_voices,
+                };
+                let patrol_voices = match item.0.event_id.and_then(|id| events.get(&id)) {
+                    Some(event) => {
+                        format!("    Event #{} {}\n{}", event.id, event.name, patrol_voices)
+                    }
+                    None => patrol_voices,
+                };
+
                 // Combine the data for this patrol, including the patrol_voice objects
                 format!(
-                    "{}{} - {}\n{}\n",
+                    "{}#{} {} - {}\n{}\n",
                     acc,
+                    item.0.id,
                     item.0.start,
                     patrol_duration,
                     &patrol_voices[0..patrol_voices.len().saturating_sub(1)]
@@ -104,18 +175,209 @@ pub async fn patrol_time(
         }
         false => {
             let patrol_time = bs::patrol_measure::get_patrol_time(
+                &ctx.data().db,
                 from_date.and_hms(0, 0, 0),
                 to_date.and_hms(23, 59, 59),
                 officer.id,
             )
             .await?;
-            display_duration_multiline(patrol_time)
+
+            // Time spent hosting events off patrol counts as well if that is turned on
+            let host_time = match CONFIG.events.count_host_time {
+                true => {
+                    bs::event::get_host_time(
+                        &ctx.data().db,
+                        from_date.and_hms(0, 0, 0),
+                        to_date.and_hms(23, 59, 59),
+                        officer.id,
+                    )
+                    .await?
+                }
+                false => 0,
+            };
+            let host_str = match host_time {
+                0 => String::new(),
+                _ => format!("\nHosting events off patrol: {}", display_duration(host_time)),
+            };
+            let removed_str = match patrol_time.removed {
+                0 => String::new(),
+                _ => format!("\nRemoved by hand: {}", display_duration(patrol_time.removed)),
+            };
+            format!(
+                "{}\nDeafened: {}{}{}\nCounted towards the requirements: {}",
+                display_duration_multiline(patrol_time.active),
+                display_duration(patrol_time.deafened),
+                removed_str,
+                host_str,
+                display_duration(patrol_time.counted() + host_time)
+            )
         }
     };
 
-    let message =
-        format!("On duty time for {} - from {} to {}:\n{}", officer, from_date, to_date, time_str);
+    // Add how the time was spread over the voice channels
+    let breakdown_str = match breakdown.unwrap_or(false) {
+        true => format!("\n{}", channel_breakdown(ctx, from_date, to_date, &officer).await?),
+        false => String::new(),
+    };
+
+    // Show the leaves of absence of the officer in the same time period
+    let loas =
+        bs::loa::get_loas(&ctx.data().db, Some(officer.id), from_date, to_date, true).await?;
+    let loa_str = loas.iter().fold(String::new(), |acc, loa| {
+        format!("{}\nLeave of absence {}", acc, bs::loa::display_loa(loa))
+    });
+
+    // Show how many events the officer attended in the same time period
+    let events_attended = bs::event::count_events_attended(
+        &ctx.data().db,
+        from_date.and_hms(0, 0, 0),
+        to_date.and_hms(23, 59, 59),
+        officer.id,
+    )
+    .await?;
+
+    let message = format!(
+        "On duty time for {} - from {} to {}:\n{}{}\nEvents attended: {}{}",
+        officer, from_date, to_date, time_str, breakdown_str, events_attended, loa_str
+    );
     send_long(ctx, &message).await?;
 
     Ok(())
 }
+
+/// List the officers that don't meet the patrol requirements.
+///
+/// Days spent on an approved leave of absence lower the requirement by the same share of the time
+/// period, officers on leave for all of it are left out.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "Time",
+    check = "is_leadership"
+)]
+pub async fn inactivity(
+    ctx: Context<'_>,
+    #[description = "The number of days to look back for activity, defaults to the settings."]
+    days: Option<i64>,
+    #[description = "The hours of patrol time required, defaults to the settings."]
+    required_hours: Option<f64>,
+) -> Result<(), Error> {
+    // Setup the parameters
+    let days = days.unwrap_or(CONFIG.activity.window_days);
+    let required_hours = required_hours.unwrap_or(CONFIG.activity.required_hours);
+    let report = bs::activity_report::inactivity_report(
+        &ctx.data().db,
+        &ctx.data().officer_cache,
+        days,
+        required_hours,
+    )
+    .await?;
+    send_long(ctx, &display_inactivity_report(&report)).await?;
+
+    Ok(())
+}
+
+/// List everyone that is on duty at the moment, with their channel and how long they have been on
+/// duty.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Time")]
+pub async fn on_duty(ctx: Context<'_>) -> Result<(), Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let message =
+        bs::status_board::make_on_duty_list(&ctx.data().patrol_cache, &ctx.discord().cache, now)
+            .await;
+    ctx.say(message).await?;
+    Ok(())
+}
+
+/// The time periods that can be picked for the leaderboard.
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum LeaderboardPeriod {
+    #[name = "Week"]
+    Week,
+    #[name = "Month"]
+    Month,
+}
+
+/// The number of officers shown on each page of the leaderboard.
+const LEADERBOARD_PAGE_SIZE: usize = 20;
+
+/// Rank the officers by their patrol time.
+///
+/// The period defaults to the last month, a custom range can be given with the from and to dates
+/// instead. Both dates are included as whole days, the same as in patrol_time.
+#[poise::command(prefix_command, slash_command, track_edits, category = "Time")]
+pub async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "The period to rank the officers over, this defaults to a month if no dates are given."]
+    period: Option<LeaderboardPeriod>,
+    #[description = "From date in the format YYYY-MM-DD. This can be given instead of the period."]
+    from_date: Option<chrono::NaiveDate>,
+    #[description = "To date in the format YYYY-MM-DD. This is set to the current date if it isn't given."]
+    to_date: Option<chrono::NaiveDate>,
+) -> Result<(), Error> {
+    // Setup the parameters
+    let to_date = to_date.unwrap_or_else(|| chrono::Utc::now().naive_utc().date());
+    let from_date = match (period, from_date) {
+        (Some(_), Some(_)) => {
+            return Err("period and from_date can't both be provided at the same time.".into());
+        }
+        (None, Some(from_date)) => from_date,
+        (Some(LeaderboardPeriod::Week), None) => date_from_days(7)?,
+        (Some(LeaderboardPeriod::Month), None) | (None, None) => date_from_days(30)?,
+    };
+
+    // Get the ranking
+    let entries = bs::patrol_measure::get_leaderboard(
+        &ctx.data().db,
+        from_date.and_hms(0, 0, 0),
+        to_date.and_hms(23, 59, 59),
+    )
+    .await?;
+    if entries.is_empty() {
+        ctx.say(format!("Nobody patrolled from {} to {}.", from_date, to_date)).await?;
+        return Ok(());
+    }
+
+    // Make a line for each officer and split them up into pages
+    let cache = &ctx.discord().cache;
+    let lines: Vec<_> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let name = cache
+                .member_field(CONFIG.guild_id, entry.officer_id, |m| m.display_name().into_owned())
+                .unwrap_or_else(|| format!("<@{}>", entry.officer_id));
+            format!("{}. {} - {}", index + 1, name, display_duration(entry.patrol_time))
+        })
+        .collect();
+    let pages: Vec<_> = lines
+        .chunks(LEADERBOARD_PAGE_SIZE)
+        .map(|page_lines| {
+            format!(
+                "Patrol time leaderboard - from {} to {}:\n{}",
+                from_date,
+                to_date,
+                page_lines.join("\n")
+            )
+        })
+        .collect();
+    send_paginated(ctx, &pages).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_breakdown() {
+        let times = vec![("At Station".to_owned(), 900), ("Patrol".to_owned(), 2700)];
+        assert_eq!(
+            display_breakdown("Time per channel", times, 3600),
+            "Time per channel:\n    Patrol - 0:0:0:45:0 (75.0%)\n    At Station - 0:0:0:15:0 (25.0%)\n"
+        );
+        assert_eq!(display_breakdown("Time per category", Vec::new(), 0), "Time per category:\n");
+    }
+}
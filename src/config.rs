@@ -2,8 +2,9 @@ use figment::{
     providers::{Env, Format, Toml},
     Figment,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::collections::HashSet;
+use std::str::FromStr;
 
 #[cfg(not(test))]
 lazy_static! {
@@ -17,6 +18,7 @@ lazy_static! {
 #[derive(Debug, Deserialize, Clone)]
 pub struct RoleConfig {
     pub lpd: u64,
+    pub leadership: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -25,6 +27,95 @@ pub struct PatrolTime {
     pub monitored_channels: HashSet<u64>,
     pub ignored_channels: HashSet<u64>,
     pub bad_main_channel_starts: Vec<String>,
+    pub heartbeat_seconds: u64,
+    pub disconnect_grace_seconds: u64,
+    pub min_patrol_seconds: i64,
+    pub min_segment_seconds: i64,
+    pub count_deafened_time: bool,
+    pub write_retries: u32,
+    pub write_retry_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ActivityConfig {
+    pub required_hours: f64,
+    pub window_days: i64,
+    /// First day of a requirement period, the periods after it are window_days long each
+    #[serde(deserialize_with = "deserialize_date")]
+    pub period_anchor: chrono::NaiveDate,
+    pub warning_days: Vec<i64>,
+    pub final_notice_days: i64,
+    /// Where officers are pinged instead if their warning can't be sent to them directly
+    pub warning_channel: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_seconds: u64,
+    pub idle_timeout_seconds: u64,
+    pub max_lifetime_seconds: u64,
+    pub sqlx_logging: bool,
+}
+
+/// Parse a date in the format YYYY-MM-DD.
+fn deserialize_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<chrono::NaiveDate, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    chrono::NaiveDate::from_str(&date).map_err(serde::de::Error::custom)
+}
+
+/// Parse a cron schedule while loading the config so a bad schedule stops the bot from starting.
+fn deserialize_schedule<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<cron::Schedule, D::Error> {
+    let schedule = String::deserialize(deserializer)?;
+    cron::Schedule::from_str(&schedule).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JobConfig {
+    #[serde(deserialize_with = "deserialize_schedule")]
+    pub schedule: cron::Schedule,
+    pub channel: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SchedulerConfig {
+    pub tick_seconds: u64,
+    pub retry_seconds: i64,
+    pub max_attempts: usize,
+    pub activity_summary: JobConfig,
+    pub inactivity_list: JobConfig,
+    pub consistency_check: JobConfig,
+    pub activity_warnings: JobConfig,
+    pub event_occurrences: JobConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatusBoardConfig {
+    pub enabled: bool,
+    pub channel: u64,
+    pub update_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClaimConfig {
+    /// Where leadership approves or denies the claims for missing patrol time
+    pub review_channel: u64,
+    /// How many days back officers can claim missing patrol time
+    pub max_age_days: i64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventConfig {
+    /// Whether the time officers spend hosting events counts towards the activity requirements,
+    /// on top of the time they are on patrol
+    pub count_host_time: bool,
+    /// How many days ahead the events of recurring events are created
+    pub create_days_ahead: i64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -32,8 +123,15 @@ pub struct Config {
     pub token: String,
     pub guild_id: u64,
     pub guild_error_text: String,
+    pub shutdown_timeout_seconds: u64,
     pub roles: RoleConfig,
+    pub database: DatabaseConfig,
     pub patrol_time: PatrolTime,
+    pub activity: ActivityConfig,
+    pub scheduler: SchedulerConfig,
+    pub status_board: StatusBoardConfig,
+    pub claims: ClaimConfig,
+    pub events: EventConfig,
 }
 /// Calculate the parent folder path with a slash at the end.
 /// Returns an empty string if the path sent in doesn't include any folder and is just a file.
@@ -78,4 +176,14 @@ mod tests {
         assert_eq!(get_parent_folder("settings/main.toml"), "settings/".to_owned());
         assert_eq!(get_parent_folder("/settings/main.toml"), "/settings/".to_owned());
     }
+
+    #[test]
+    fn test_job_schedules() {
+        let now = chrono::Utc::now();
+        assert!(CONFIG.scheduler.activity_summary.schedule.after(&now).next().is_some());
+        assert!(CONFIG.scheduler.inactivity_list.schedule.after(&now).next().is_some());
+        assert!(CONFIG.scheduler.consistency_check.schedule.after(&now).next().is_some());
+        assert!(CONFIG.scheduler.activity_warnings.schedule.after(&now).next().is_some());
+        assert!(CONFIG.scheduler.event_occurrences.schedule.after(&now).next().is_some());
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::CONFIG;
 use dotenv::dotenv;
 use entity::sea_orm::ConnectOptions;
 use entity::sea_orm::Database;
@@ -5,18 +6,20 @@ use entity::sea_orm::DatabaseConnection;
 use std::env;
 use std::time::Duration;
 
+/// Connect to the database, the pool returned is meant to be created once and shared.
 pub async fn establish_connection() -> DatabaseConnection {
     dotenv().ok();
 
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
+    let db_config = &CONFIG.database;
     let mut opt = ConnectOptions::new(database_url.to_owned());
-    opt.max_connections(100)
-        .min_connections(5)
-        .connect_timeout(Duration::from_secs(8))
-        .idle_timeout(Duration::from_secs(8))
-        .max_lifetime(Duration::from_secs(8))
-        .sqlx_logging(true);
+    opt.max_connections(db_config.max_connections)
+        .min_connections(db_config.min_connections)
+        .connect_timeout(Duration::from_secs(db_config.connect_timeout_seconds))
+        .idle_timeout(Duration::from_secs(db_config.idle_timeout_seconds))
+        .max_lifetime(Duration::from_secs(db_config.max_lifetime_seconds))
+        .sqlx_logging(db_config.sqlx_logging);
 
     Database::connect(opt)
         .await
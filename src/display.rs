@@ -0,0 +1,144 @@
+use crate::business::activity_report::{ActivitySummary, InactivityReport};
+use crate::config::CONFIG;
+use poise::serenity_prelude as serenity;
+
+/// The most characters Discord allows in a single message.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Split a message up into parts that fit into a Discord message, breaking it up between lines.
+///
+/// Lines that are too long on their own are broken up wherever the limit is reached.
+pub fn split_message(message: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    for line in message.lines() {
+        let mut line: Vec<char> = line.chars().collect();
+        loop {
+            let part = parts.last_mut().expect("There is always at least one part");
+            let part_length = part.chars().count();
+            let separator = usize::from(part_length > 0);
+            if part_length + separator + line.len() <= MAX_MESSAGE_LENGTH {
+                if separator > 0 {
+                    part.push('\n');
+                }
+                part.extend(line);
+                break;
+            }
+            if part_length > 0 {
+                parts.push(String::new());
+                continue;
+            }
+            let rest = line.split_off(MAX_MESSAGE_LENGTH);
+            part.extend(line);
+            parts.push(String::new());
+            line = rest;
+        }
+    }
+    parts
+}
+
+/// Convert seconds into weeks, days, hours, minutes and seconds
+pub fn split_duration(seconds: i64) -> (i64, i64, i64, i64, i64) {
+    // Calculate each duration
+    let (min_rem, seconds) = (seconds / 60, seconds % 60);
+    let (hour_rem, minutes) = (min_rem / 60, min_rem % 60);
+    let (day_rem, hours) = (hour_rem / 60, hour_rem % 60);
+    let (weeks, days) = (day_rem / 60, day_rem % 60);
+
+    // Return the values
+    (weeks, days, hours, minutes, seconds)
+}
+
+/// Make a single line string that represents a duration
+pub fn display_duration(seconds: i64) -> String {
+    let (weeks, days, hours, minutes, seconds) = split_duration(seconds);
+
+    // Convert the duration into a single line string
+    format!("{}:{}:{}:{}:{}", weeks, days, hours, minutes, seconds)
+}
+
+/// Make the message listing the officers that don't meet the patrol requirements.
+pub fn display_inactivity_report(report: &InactivityReport) -> String {
+    // Make a line for each of them
+    let lines: Vec<_> = report
+        .officers
+        .iter()
+        .map(|item| {
+            let last_patrol = match item.last_patrol {
+                Some(end) => end.date().to_string(),
+                None => "never".to_owned(),
+            };
+            let loa = match item.loa_days {
+                0 => "".to_owned(),
+                _ => format!(" - on leave for {} days", item.loa_days),
+            };
+            let monitored_days =
+                report.to.signed_duration_since(item.officer.started_monitoring).num_days();
+            format!(
+                "<@{}> - {} - last patrol: {} - monitored for {} days{}",
+                item.officer.id,
+                display_duration(item.counted),
+                last_patrol,
+                monitored_days,
+                loa
+            )
+        })
+        .collect();
+
+    match lines.is_empty() {
+        true => format!(
+            "Everyone has at least {} hours in the last {} days.",
+            report.required_hours, report.days
+        ),
+        false => format!(
+            "Officers with less than {} hours in the last {} days:\n{}",
+            report.required_hours,
+            report.days,
+            lines.join("\n")
+        ),
+    }
+}
+
+/// Make the message summing up the patrol activity of the whole department.
+pub fn display_activity_summary(
+    summary: &ActivitySummary,
+    discord_cache: &serenity::Cache,
+) -> String {
+    let total_time = summary.entries.iter().fold(0, |acc, entry| acc + entry.patrol_time);
+
+    // Show who patrolled the most
+    let top_officers =
+        summary.entries.iter().take(5).enumerate().fold(String::new(), |acc, (index, entry)| {
+            let name = discord_cache
+                .member_field(CONFIG.guild_id, entry.officer_id, |m| m.display_name().into_owned())
+                .unwrap_or_else(|| format!("<@{}>", entry.officer_id));
+            format!("{}\n{}. {} - {}", acc, index + 1, name, display_duration(entry.patrol_time))
+        });
+
+    format!(
+        "Department activity from {} to {}:\n{} of {} officers patrolled for a total of {}{}",
+        summary.from_date,
+        summary.to.date(),
+        summary.entries.len(),
+        summary.officer_count,
+        display_duration(total_time),
+        top_officers
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("a\nb"), vec!["a\nb".to_owned()]);
+
+        let line = "a".repeat(1500);
+        let parts = split_message(&format!("{}\n{}", line, line));
+        assert_eq!(parts, vec![line.clone(), line]);
+
+        let parts = split_message(&"b".repeat(4500));
+        let lengths: Vec<_> = parts.iter().map(|part| part.len()).collect();
+        assert_eq!(lengths, vec![2000, 2000, 500]);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::business::patrol_measure;
+use entity::sea_orm::DatabaseConnection;
+use poise::serenity_prelude as serenity;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -9,6 +11,10 @@ pub type PatrolCache = Arc<RwLock<HashMap<u64, patrol_measure::PatrolLog>>>;
 pub struct Data {
     pub officer_cache: OfficerCache,
     pub patrol_cache: PatrolCache,
+    /// Serenity's cache, kept here so it can still be used after the client has been shut down
+    pub discord_cache: Arc<serenity::Cache>,
+    /// Connection pool shared by everything that talks to the database
+    pub db: DatabaseConnection,
 }
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
@@ -2,13 +2,16 @@ mod business;
 mod commands;
 mod config;
 mod db;
+mod display;
 mod global;
+mod scheduler;
 
 use crate::global::{Context, Data, Error};
 use poise::serenity_prelude as serenity;
 
 use std::boxed::Box;
 use std::string::String;
+use std::time::Duration;
 use std::vec::Vec;
 
 // use entity::sea_orm::ColumnTrait;
@@ -45,6 +48,9 @@ async fn event_listener(
 ) -> Result<(), Error> {
     business::member_management::event_listener(ctx, event, framework, user_data).await?;
     business::patrol_measure::event_listener(ctx, event, framework, user_data).await?;
+    business::voice_channel::event_listener(ctx, event, framework, user_data).await?;
+    business::claim::event_listener(ctx, event, framework, user_data).await?;
+    business::scheduled_event::event_listener(ctx, event, framework, user_data).await?;
 
     if let poise::Event::Ready { data_about_bot } = event {
         println!("{} is connected!", data_about_bot.user.name);
@@ -53,6 +59,54 @@ async fn event_listener(
     Ok(())
 }
 
+/// Wait until the process is asked to stop, returning the name of the signal that was received.
+async fn wait_for_shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM.");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = sigterm.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C.");
+        "SIGINT"
+    }
+}
+
+/// Stop the bot, closing every patrol that is still going before returning.
+async fn shutdown(framework: &poise::Framework<Data, Error>, signal: &str) {
+    let shutdown_time = chrono::Utc::now().naive_utc();
+    println!("Received {}, shutting down", signal);
+
+    // Stop receiving events so the patrol cache doesn't change while it is being closed
+    framework.shard_manager().lock().await.shutdown_all().await;
+
+    // Close the patrols without letting a slow database block the shutdown forever, anything that
+    // doesn't make it gets recovered from the active patrols on the next start
+    let timeout = Duration::from_secs(config::CONFIG.shutdown_timeout_seconds);
+    let close_patrols = async {
+        let user_data = framework.user_data().await;
+        business::patrol_measure::close_all_patrols(
+            &user_data.db,
+            &user_data.patrol_cache,
+            &user_data.discord_cache,
+            shutdown_time,
+        )
+        .await;
+    };
+    match tokio::time::timeout(timeout, close_patrols).await {
+        Ok(()) => println!("All patrols closed, shutdown complete"),
+        Err(_) => println!(
+            "Closing the patrols timed out after {} seconds, shutting down anyway",
+            timeout.as_secs()
+        ),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     // Setup logging
@@ -62,7 +116,7 @@ async fn main() {
     //     .with_test_writer()
     //     .init();
 
-    poise::Framework::build()
+    let framework = poise::Framework::build()
         .token(&config::CONFIG.token)
         .user_data_setup(move |ctx, _ready, framework| {
             Box::pin(async move {
@@ -77,11 +131,38 @@ async fn main() {
                     .await
                     .unwrap();
 
+                // Connect to the database once, everything shares this pool
+                let db = db::establish_connection().await;
+
+                // Keep track of when the bot was last alive for recovering patrols after a crash
+                tokio::spawn(business::patrol_measure::heartbeat(db.clone()));
+
+                // Ready the caches
+                let officer_cache = business::member_management::cache_init(&db).await;
+                let patrol_cache = business::patrol_measure::cache_init(&db).await;
+
+                // Run the recurring reports
+                let scheduler = scheduler::Scheduler {
+                    conn: db.clone(),
+                    http: ctx.http.clone(),
+                    discord_cache: ctx.cache.clone(),
+                    officer_cache: officer_cache.clone(),
+                    patrol_cache: patrol_cache.clone(),
+                };
+                tokio::spawn(scheduler.run());
+
+                // Keep the list of everyone on duty up to date
+                if config::CONFIG.status_board.enabled {
+                    tokio::spawn(business::status_board::run(
+                        db.clone(),
+                        ctx.http.clone(),
+                        ctx.cache.clone(),
+                        patrol_cache.clone(),
+                    ));
+                }
+
                 // Ready the user data
-                Ok(Data {
-                    officer_cache: business::member_management::cache_init().await,
-                    patrol_cache: business::patrol_measure::cache_init().await,
-                })
+                Ok(Data { officer_cache, patrol_cache, discord_cache: ctx.cache.clone(), db })
             })
         })
         .options(poise::FrameworkOptions {
@@ -93,7 +174,33 @@ async fn main() {
                 )),
                 ..Default::default()
             },
-            commands: vec![help(), commands::patrol_time(), commands::rtv()],
+            commands: vec![
+                help(),
+                commands::patrol_time(),
+                commands::inactivity(),
+                commands::leaderboard(),
+                commands::on_duty(),
+                commands::add_time(),
+                commands::remove_time(),
+                commands::undo_adjustment(),
+                commands::adjustments(),
+                commands::claim_time(),
+                commands::event_create(),
+                commands::event_edit(),
+                commands::event_clear(),
+                commands::event_cancel(),
+                commands::event_list(),
+                commands::event_attendance(),
+                commands::host_stats(),
+                commands::recurring_event_create(),
+                commands::recurring_event_list(),
+                commands::recurring_event_end(),
+                commands::loa_request(),
+                commands::loa_approve(),
+                commands::loa_list(),
+                commands::loa_end(),
+                commands::rtv(),
+            ],
             listener: |ctx, event, framework, user_data| {
                 Box::pin(event_listener(ctx, event, framework, user_data))
             },
@@ -103,9 +210,16 @@ async fn main() {
             serenity::GatewayIntents::non_privileged()
                 | serenity::GatewayIntents::MESSAGE_CONTENT
                 | serenity::GatewayIntents::GUILD_MEMBERS
-                | serenity::GatewayIntents::GUILD_PRESENCES,
+                | serenity::GatewayIntents::GUILD_PRESENCES
+                | serenity::GatewayIntents::GUILD_SCHEDULED_EVENTS,
         )
-        .run()
+        .build()
         .await
         .unwrap();
+
+    // Run the bot until it stops by itself or gets asked to shut down
+    tokio::select! {
+        result = framework.clone().start() => result.unwrap(),
+        signal = wait_for_shutdown_signal() => shutdown(&framework, signal).await,
+    }
 }
@@ -0,0 +1,260 @@
+use crate::business as bs;
+use crate::config::{JobConfig, CONFIG};
+use crate::display;
+use crate::global::{Error, OfficerCache, PatrolCache};
+use entity::job_run;
+use entity::sea_orm::ColumnTrait;
+use entity::sea_orm::DatabaseConnection;
+use entity::sea_orm::EntityTrait;
+use entity::sea_orm::PaginatorTrait;
+use entity::sea_orm::QueryFilter;
+use entity::sea_orm::QueryOrder;
+use poise::serenity_prelude as serenity;
+
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The jobs that run on the schedules in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Job {
+    ActivitySummary,
+    InactivityList,
+    ConsistencyCheck,
+    ActivityWarnings,
+    EventOccurrences,
+}
+
+impl Job {
+    const ALL: [Job; 5] = [
+        Job::ActivitySummary,
+        Job::InactivityList,
+        Job::ConsistencyCheck,
+        Job::ActivityWarnings,
+        Job::EventOccurrences,
+    ];
+
+    /// The name the runs of this job are stored under in the database.
+    fn name(&self) -> &'static str {
+        match self {
+            Job::ActivitySummary => "activity_summary",
+            Job::InactivityList => "inactivity_list",
+            Job::ConsistencyCheck => "consistency_check",
+            Job::ActivityWarnings => "activity_warnings",
+            Job::EventOccurrences => "event_occurrences",
+        }
+    }
+
+    fn config(&self) -> &'static JobConfig {
+        match self {
+            Job::ActivitySummary => &CONFIG.scheduler.activity_summary,
+            Job::InactivityList => &CONFIG.scheduler.inactivity_list,
+            Job::ConsistencyCheck => &CONFIG.scheduler.consistency_check,
+            Job::ActivityWarnings => &CONFIG.scheduler.activity_warnings,
+            Job::EventOccurrences => &CONFIG.scheduler.event_occurrences,
+        }
+    }
+}
+
+/// Get the last time a schedule was due after the time given, up to and including now.
+///
+/// Only the last time is returned when several were missed, catching up runs a job once.
+fn due_occurrence(
+    schedule: &cron::Schedule,
+    after: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    schedule.after(&after).take_while(|time| *time <= now).last()
+}
+
+/// Runs the jobs in the settings on their schedules, posting the results to their channels.
+///
+/// Every run is recorded in the database, runs that were missed while the bot was down are caught
+/// up once it is back and failed runs are retried a few times.
+pub struct Scheduler {
+    pub conn: DatabaseConnection,
+    pub http: Arc<serenity::Http>,
+    pub discord_cache: Arc<serenity::Cache>,
+    pub officer_cache: OfficerCache,
+    pub patrol_cache: PatrolCache,
+}
+
+impl Scheduler {
+    /// Check for jobs that are due forever.
+    pub async fn run(self) {
+        let started = Utc::now();
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(CONFIG.scheduler.tick_seconds));
+        loop {
+            interval.tick().await;
+            for job in Job::ALL {
+                if let Err(err) = self.run_if_due(job, started).await {
+                    println!("Failed checking if the {} job is due: {}", job.name(), err);
+                }
+            }
+        }
+    }
+
+    /// Run a job if it is due or if its last run failed and can be retried.
+    ///
+    /// Jobs that have never run before only become due after the scheduler started.
+    async fn run_if_due(&self, job: Job, started: DateTime<Utc>) -> Result<(), Error> {
+        let now = Utc::now();
+        let last_run = job_run::Entity::find()
+            .filter(job_run::Column::JobName.eq(job.name()))
+            .order_by_desc(job_run::Column::ScheduledFor)
+            .order_by_desc(job_run::Column::Id)
+            .one(&self.conn)
+            .await?;
+
+        // Run the job if it was due since the last run
+        let after = match &last_run {
+            Some(last_run) => DateTime::from_utc(last_run.scheduled_for, Utc),
+            None => started,
+        };
+        if let Some(scheduled_for) = due_occurrence(&job.config().schedule, after, now) {
+            return self.run_job(job, scheduled_for).await;
+        }
+
+        // Otherwise retry the last run if it failed, runs that never finished count as failed
+        // once the retry time has passed as the bot may have stopped in the middle of them
+        let last_run = match last_run {
+            Some(last_run) if last_run.error.is_some() || last_run.finished_at.is_none() => {
+                last_run
+            }
+            _ => return Ok(()),
+        };
+        let retry_after = chrono::Duration::seconds(CONFIG.scheduler.retry_seconds);
+        if now.naive_utc().signed_duration_since(last_run.started_at) < retry_after {
+            return Ok(());
+        }
+        let attempts = job_run::Entity::find()
+            .filter(job_run::Column::JobName.eq(job.name()))
+            .filter(job_run::Column::ScheduledFor.eq(last_run.scheduled_for))
+            .count(&self.conn)
+            .await?;
+        if attempts >= CONFIG.scheduler.max_attempts {
+            return Ok(());
+        }
+        self.run_job(job, DateTime::from_utc(last_run.scheduled_for, Utc)).await
+    }
+
+    /// Run a job and post the result to its channel, recording the run in the database.
+    async fn run_job(&self, job: Job, scheduled_for: DateTime<Utc>) -> Result<(), Error> {
+        println!("Running the {} job scheduled for {}", job.name(), scheduled_for);
+
+        use entity::sea_orm::entity::*;
+        let run = job_run::ActiveModel {
+            job_name: Set(job.name().to_owned()),
+            scheduled_for: Set(scheduled_for.naive_utc()),
+            started_at: Set(Utc::now().naive_utc()),
+            finished_at: Set(None),
+            error: Set(None),
+            ..Default::default()
+        }
+        .insert(&self.conn)
+        .await?;
+
+        let result = match self.make_report(job).await {
+            Ok(report) => self.post(job, &report).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = &result {
+            println!("The {} job failed: {}", job.name(), err);
+        }
+
+        let mut run: job_run::ActiveModel = run.into();
+        run.finished_at = Set(Some(Utc::now().naive_utc()));
+        run.error = Set(result.err().map(|err| err.to_string()));
+        run.update(&self.conn).await?;
+
+        Ok(())
+    }
+
+    /// Make the message a job posts.
+    async fn make_report(&self, job: Job) -> Result<String, Error> {
+        let conn = &self.conn;
+        match job {
+            Job::ActivitySummary => {
+                let summary =
+                    bs::activity_report::activity_summary_report(conn, &self.officer_cache, 7)
+                        .await?;
+                Ok(display::display_activity_summary(&summary, &self.discord_cache))
+            }
+            Job::InactivityList => {
+                let report = bs::activity_report::inactivity_report(
+                    conn,
+                    &self.officer_cache,
+                    CONFIG.activity.window_days,
+                    CONFIG.activity.required_hours,
+                )
+                .await?;
+                Ok(display::display_inactivity_report(&report))
+            }
+            Job::ConsistencyCheck => {
+                let problems =
+                    bs::patrol_measure::check_consistency(conn, &self.patrol_cache).await?;
+                Ok(match problems.is_empty() {
+                    true => "Database consistency check: no problems found.".to_owned(),
+                    false => format!("Database consistency check:\n{}", problems.join("\n")),
+                })
+            }
+            Job::ActivityWarnings => {
+                let now = Utc::now().naive_utc();
+                let summary = bs::activity_warning::send_activity_warnings(
+                    conn,
+                    &self.http,
+                    &self.officer_cache,
+                    now,
+                )
+                .await?;
+                Ok(format!(
+                    "Activity warnings: {} sent, {} of them as a ping as the DM failed, {} failed.",
+                    summary.sent, summary.pinged, summary.failed
+                ))
+            }
+            Job::EventOccurrences => {
+                let today = Utc::now().naive_utc().date();
+                let (updated, failed) = bs::event_template::create_occurrences(conn, today).await?;
+                Ok(format!(
+                    "Recurring events: created the upcoming events of {}, {} failed.",
+                    updated, failed
+                ))
+            }
+        }
+    }
+
+    /// Post a message in the channel of a job.
+    async fn post(&self, job: Job, message: &str) -> Result<(), Error> {
+        let channel_id = serenity::ChannelId(job.config().channel);
+        for part in display::split_message(message) {
+            channel_id.say(&self.http, part).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_due_occurrence() {
+        // Every day at 04:00
+        let schedule = cron::Schedule::from_str("0 0 4 * * *").unwrap();
+        let after = Utc.ymd(2022, 5, 1).and_hms(4, 0, 0);
+
+        // Not due again until the next day
+        let now = Utc.ymd(2022, 5, 2).and_hms(3, 59, 59);
+        assert_eq!(due_occurrence(&schedule, after, now), None);
+        let now = Utc.ymd(2022, 5, 2).and_hms(4, 0, 0);
+        assert_eq!(due_occurrence(&schedule, after, now), Some(now));
+
+        // Missed runs are only caught up once
+        let now = Utc.ymd(2022, 5, 5).and_hms(12, 0, 0);
+        let last_missed = Utc.ymd(2022, 5, 5).and_hms(4, 0, 0);
+        assert_eq!(due_occurrence(&schedule, after, now), Some(last_missed));
+    }
+}